@@ -0,0 +1,11 @@
+CREATE TABLE IF NOT EXISTS chat_messages (
+    id INTEGER PRIMARY KEY AUTOINCREMENT,
+    chat_id INTEGER NOT NULL,
+    conversation_id INTEGER NOT NULL DEFAULT 0,
+    role TEXT NOT NULL CHECK (role IN ('system','user','assistant','tool')),
+    content TEXT NOT NULL,
+    created_at INTEGER NOT NULL DEFAULT (strftime('%s','now'))
+);
+
+CREATE INDEX IF NOT EXISTS idx_chat_messages_chat
+    ON chat_messages (chat_id, conversation_id, id);
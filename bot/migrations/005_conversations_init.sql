@@ -0,0 +1,26 @@
+CREATE TABLE IF NOT EXISTS conversations (
+    id INTEGER PRIMARY KEY AUTOINCREMENT,
+    chat_id INTEGER NOT NULL,
+    name TEXT NOT NULL,
+    created_at INTEGER NOT NULL DEFAULT (strftime('%s','now')),
+    UNIQUE(chat_id, name)
+);
+
+CREATE TABLE IF NOT EXISTS chat_settings (
+    chat_id INTEGER PRIMARY KEY,
+    active_conversation_id INTEGER,
+    FOREIGN KEY(active_conversation_id) REFERENCES conversations(id) ON DELETE SET NULL
+);
+
+INSERT OR IGNORE INTO conversations (chat_id, name)
+SELECT DISTINCT chat_id, 'default' FROM chat_messages;
+
+UPDATE chat_messages
+SET conversation_id = (
+    SELECT c.id FROM conversations c
+    WHERE c.chat_id = chat_messages.chat_id AND c.name = 'default'
+)
+WHERE conversation_id = 0;
+
+INSERT OR IGNORE INTO chat_settings (chat_id, active_conversation_id)
+SELECT chat_id, id FROM conversations;
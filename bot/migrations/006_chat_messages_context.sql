@@ -0,0 +1,4 @@
+ALTER TABLE chat_messages ADD COLUMN tokens INTEGER NOT NULL DEFAULT 0;
+
+ALTER TABLE chat_messages ADD COLUMN kind TEXT NOT NULL DEFAULT 'message'
+    CHECK (kind IN ('message','summary'));
@@ -0,0 +1,2 @@
+ALTER TABLE chat_messages ADD COLUMN tool_calls TEXT;
+ALTER TABLE chat_messages ADD COLUMN tool_call_id TEXT;
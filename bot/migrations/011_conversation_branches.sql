@@ -0,0 +1,8 @@
+ALTER TABLE conversations ADD COLUMN parent_id INTEGER;
+
+CREATE TABLE IF NOT EXISTS telegram_messages (
+    chat_id INTEGER NOT NULL,
+    telegram_message_id INTEGER NOT NULL,
+    chat_message_id INTEGER NOT NULL,
+    PRIMARY KEY (chat_id, telegram_message_id)
+);
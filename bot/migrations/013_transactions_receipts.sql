@@ -0,0 +1 @@
+ALTER TABLE transactions ADD COLUMN receipt_file_id TEXT;
@@ -0,0 +1,13 @@
+ALTER TABLE chat_settings ADD COLUMN timezone TEXT;
+
+CREATE TABLE IF NOT EXISTS jobs (
+    id INTEGER PRIMARY KEY AUTOINCREMENT,
+    chat_id INTEGER NOT NULL,
+    schedule TEXT NOT NULL,
+    prompt TEXT NOT NULL,
+    next_run INTEGER NOT NULL,
+    created_at INTEGER NOT NULL DEFAULT (strftime('%s','now'))
+);
+
+CREATE INDEX IF NOT EXISTS idx_jobs_next_run
+    ON jobs (next_run);
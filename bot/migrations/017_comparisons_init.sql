@@ -0,0 +1,22 @@
+CREATE TABLE IF NOT EXISTS comparisons (
+    id INTEGER PRIMARY KEY AUTOINCREMENT,
+    chat_id INTEGER NOT NULL,
+    conversation_id INTEGER NOT NULL,
+    prompt TEXT NOT NULL,
+    picked_answer_id INTEGER,
+    created_at INTEGER NOT NULL DEFAULT (strftime('%s','now')),
+    FOREIGN KEY(conversation_id) REFERENCES conversations(id) ON DELETE CASCADE
+);
+
+CREATE TABLE IF NOT EXISTS comparison_answers (
+    id INTEGER PRIMARY KEY AUTOINCREMENT,
+    comparison_id INTEGER NOT NULL,
+    model TEXT NOT NULL,
+    content TEXT NOT NULL,
+    message_ids TEXT NOT NULL,
+    created_at INTEGER NOT NULL DEFAULT (strftime('%s','now')),
+    FOREIGN KEY(comparison_id) REFERENCES comparisons(id) ON DELETE CASCADE
+);
+
+CREATE INDEX IF NOT EXISTS idx_comparison_answers_comparison
+    ON comparison_answers (comparison_id);
@@ -0,0 +1 @@
+ALTER TABLE comparisons ADD COLUMN last_message_id INTEGER NOT NULL DEFAULT 0;
@@ -2,19 +2,20 @@ use std::sync::Arc;
 
 use crate::{
     handlers, keyboard,
+    providers::Provider,
     types::{
         auth::AuthState,
-        common::{BotDialogue, ChatHistoryState, Commands, HandleResult},
+        chat::ActiveReplies,
+        common::{BotDialogue, Commands, HandleResult},
         databases::Database,
     },
 };
-use async_openai::{config::OpenAIConfig, Client};
 use teloxide::prelude::*;
 
 #[allow(clippy::too_many_arguments)]
 pub async fn commands(
-    client: Client<OpenAIConfig>,
-    state: ChatHistoryState,
+    provider: Provider,
+    replies: ActiveReplies,
     _auth_state: AuthState,
     bot: Bot,
     dialogue: BotDialogue,
@@ -33,10 +34,14 @@ pub async fn commands(
             handlers::dice::roll(bot, msg).await?;
         }
         Commands::Prompt(prompt) => {
-            handlers::gpt::prompt::set(prompt, bot, state, msg).await?;
+            handlers::gpt::prompt::set(prompt, bot, &db.conversations(), &db.chat_messages(), msg)
+                .await?;
         }
         Commands::Chat(content) => {
-            handlers::gpt::chat::message(content, bot, client, state, msg).await?;
+            handlers::gpt::chat::message(content, bot, provider, &db, replies, msg).await?;
+        }
+        Commands::Compare(prompt) => {
+            handlers::gpt::compare::compare(prompt, bot, provider, &db, replies, msg).await?;
         }
         Commands::Enter => {
             handlers::gpt::chat::enter(bot, dialogue, msg).await?;
@@ -45,10 +50,86 @@ pub async fn commands(
             handlers::gpt::chat::exit(bot, dialogue, msg).await?;
         }
         Commands::View => {
-            handlers::gpt::history::view(bot, state, msg).await?;
+            handlers::gpt::history::view(bot, &db.conversations(), &db.chat_messages(), msg)
+                .await?;
         }
         Commands::Clear => {
-            handlers::gpt::history::clear(bot, state, msg).await?;
+            handlers::gpt::history::clear(bot, &db.conversations(), &db.chat_messages(), msg)
+                .await?;
+        }
+        Commands::New(name) => {
+            handlers::gpt::conversations::create(name, bot, msg.chat.id, &db.conversations())
+                .await?;
+        }
+        Commands::Conversations => {
+            handlers::gpt::conversations::list(bot, msg.chat.id, &db.conversations()).await?;
+        }
+        Commands::Switch(key) => {
+            handlers::gpt::conversations::switch(key, bot, msg.chat.id, &db.conversations())
+                .await?;
+        }
+        Commands::Rename(name) => {
+            handlers::gpt::conversations::rename(name, bot, msg.chat.id, &db.conversations())
+                .await?;
+        }
+        Commands::Drop(key) => {
+            handlers::gpt::conversations::delete(key, bot, msg.chat.id, &db.conversations())
+                .await?;
+        }
+        Commands::Export(format) => {
+            handlers::gpt::transfer::export(
+                format,
+                bot,
+                msg.chat.id,
+                &db.conversations(),
+                &db.chat_messages(),
+            )
+            .await?;
+        }
+        Commands::Import => {
+            handlers::gpt::transfer::start_import(bot, msg.chat.id, dialogue).await?;
+        }
+        Commands::Documents => {
+            handlers::gpt::documents::list(bot, msg.chat.id, &db.documents()).await?;
+        }
+        Commands::Unindex(key) => {
+            handlers::gpt::documents::delete(key, bot, msg.chat.id, &db.documents()).await?;
+        }
+        Commands::Remember(content) => {
+            handlers::gpt::memories::remember(content, bot, msg.chat.id, &db.memories()).await?;
+        }
+        Commands::Forget(key) => {
+            handlers::gpt::memories::forget(key, bot, msg.chat.id, &db.memories()).await?;
+        }
+        Commands::Memories => {
+            handlers::gpt::memories::list(bot, msg.chat.id, &db.memories()).await?;
+        }
+        Commands::Schedule(text) => {
+            handlers::gpt::jobs::create(text, bot, msg.chat.id, &db.jobs()).await?;
+        }
+        Commands::Jobs => {
+            handlers::gpt::jobs::list(bot, msg.chat.id, &db.jobs()).await?;
+        }
+        Commands::Unschedule(key) => {
+            handlers::gpt::jobs::delete(key, bot, msg.chat.id, &db.jobs()).await?;
+        }
+        Commands::Timezone(name) => {
+            handlers::gpt::jobs::set_timezone(name, bot, msg.chat.id, &db.jobs()).await?;
+        }
+        Commands::Usage => {
+            handlers::gpt::usage::report(bot, &db.usage(), msg.chat.id).await?;
+        }
+        Commands::Spent(text) => {
+            handlers::budgeting::proposals::propose(
+                text,
+                None,
+                bot,
+                msg.chat.id,
+                dialogue,
+                provider,
+                &db,
+            )
+            .await?;
         }
         Commands::Delete => {
             handlers::budgeting::transactions::delete_last(bot, msg, &db.transactions()).await?;
@@ -1,18 +1,266 @@
 use anyhow::Result;
 use lazy_static::lazy_static;
 use serde::{Deserialize, Serialize};
-use std::fs;
+use std::{collections::HashMap, fs};
 
 #[derive(Clone, Serialize, Deserialize, Debug)]
 pub struct Config {
     pub open_ai: OpenAiConfig,
+    #[serde(default)]
+    pub usage: UsageConfig,
+    #[serde(default)]
+    pub transcription: TranscriptionConfig,
+    #[serde(default)]
+    pub documents: DocumentsConfig,
+    #[serde(default)]
+    pub scheduler: SchedulerConfig,
+    #[serde(default)]
+    pub groups: GroupsConfig,
     pub web: WebConfig,
     pub api: ApiConfig,
 }
 
 #[derive(Clone, Serialize, Deserialize, Debug)]
 pub struct OpenAiConfig {
+    #[serde(default)]
+    pub provider: ProviderKind,
+    #[serde(default)]
+    pub mock_responses: Vec<String>,
+    pub api_base: Option<String>,
     pub model: String,
+    #[serde(default)]
+    pub models: Vec<String>,
+    /// Models accepting image input, photos are refused for the others.
+    #[serde(default)]
+    pub vision_models: Vec<String>,
+    /// Models answering `/compare` side by side, all allowed models when empty.
+    #[serde(default)]
+    pub compare_models: Vec<String>,
+    pub temperature: Option<f32>,
+    pub max_tokens: Option<u32>,
+    pub top_p: Option<f32>,
+    /// Models rejecting `max_tokens`, their limit is sent as `max_completion_tokens`.
+    #[serde(default)]
+    pub completion_tokens_models: Vec<String>,
+    /// Asks for token usage at the end of streams, off for servers rejecting `stream_options`.
+    #[serde(default = "default_stream_usage")]
+    pub stream_usage: bool,
+    #[serde(default)]
+    pub parameters: HashMap<String, ModelParameters>,
+    pub history_limit: Option<usize>,
+    #[serde(default)]
+    pub context_strategy: ContextStrategy,
+    #[serde(default = "default_context_budget")]
+    pub default_context_budget: usize,
+    #[serde(default)]
+    pub context_budgets: HashMap<String, usize>,
+}
+
+#[derive(Clone, Copy, Serialize, Deserialize, Debug, Default, PartialEq)]
+#[serde(rename_all = "lowercase")]
+pub enum ProviderKind {
+    /// OpenAI or any OpenAI-compatible API at `api_base`.
+    #[default]
+    #[serde(rename = "openai")]
+    OpenAI,
+    /// In-process backend replying with `mock_responses`, used offline and in tests.
+    Mock,
+}
+
+/// Speech to text backend for voice messages, `api_base` may point to a local
+/// OpenAI-compatible server.
+#[derive(Clone, Serialize, Deserialize, Debug)]
+pub struct TranscriptionConfig {
+    #[serde(default)]
+    pub provider: ProviderKind,
+    pub api_base: Option<String>,
+    #[serde(default = "default_transcription_model")]
+    pub model: String,
+    pub language: Option<String>,
+    /// Text returned by the mock provider for every voice message.
+    #[serde(default)]
+    pub mock_text: String,
+}
+
+fn default_context_budget() -> usize {
+    6000
+}
+
+fn default_stream_usage() -> bool {
+    true
+}
+
+fn default_transcription_model() -> String {
+    "whisper-1".to_string()
+}
+
+impl Default for TranscriptionConfig {
+    fn default() -> Self {
+        Self {
+            provider: ProviderKind::default(),
+            api_base: None,
+            model: default_transcription_model(),
+            language: None,
+            mock_text: String::new(),
+        }
+    }
+}
+
+/// Indexing and retrieval of uploaded documents, sizes are in characters.
+#[derive(Clone, Serialize, Deserialize, Debug)]
+#[serde(default)]
+pub struct DocumentsConfig {
+    pub embedding_model: String,
+    pub chunk_size: usize,
+    pub chunk_overlap: usize,
+    /// Chunks injected into a chat request at most.
+    pub top_k: usize,
+    /// Minimal cosine similarity of an injected chunk.
+    pub min_score: f32,
+}
+
+impl Default for DocumentsConfig {
+    fn default() -> Self {
+        Self {
+            embedding_model: "text-embedding-3-small".to_string(),
+            chunk_size: 1500,
+            chunk_overlap: 200,
+            top_k: 4,
+            min_score: 0.3,
+        }
+    }
+}
+
+/// Group chats the bot answers in when mentioned or replied to, by chat id.
+#[derive(Clone, Serialize, Deserialize, Debug, Default)]
+pub struct GroupsConfig {
+    #[serde(default)]
+    pub allowed: Vec<i64>,
+}
+
+/// Background runs of scheduled prompts.
+#[derive(Clone, Serialize, Deserialize, Debug)]
+#[serde(default)]
+pub struct SchedulerConfig {
+    /// IANA timezone of schedules for chats without their own `/timezone`.
+    pub timezone: String,
+    /// Seconds between checks for due jobs.
+    pub interval_seconds: u64,
+}
+
+impl Default for SchedulerConfig {
+    fn default() -> Self {
+        Self {
+            timezone: "UTC".to_string(),
+            interval_seconds: 30,
+        }
+    }
+}
+
+#[derive(Clone, Copy, Serialize, Deserialize, Debug, Default, PartialEq)]
+#[serde(rename_all = "lowercase")]
+pub enum ContextStrategy {
+    /// Drop the oldest turns that do not fit the budget.
+    #[default]
+    Truncate,
+    /// Collapse the oldest turns into a generated summary message.
+    Summarize,
+}
+
+#[derive(Clone, Serialize, Deserialize, Debug, Default)]
+pub struct UsageConfig {
+    /// Tokens a user may spend per day, unlimited when unset.
+    pub daily_token_quota: Option<u64>,
+    /// Tokens a user may spend per calendar month, unlimited when unset.
+    pub monthly_token_quota: Option<u64>,
+    /// AI requests a chat may start per minute, unlimited when unset.
+    pub requests_per_minute: Option<usize>,
+    /// USD per million tokens by model, used for cost estimates.
+    #[serde(default)]
+    pub prices: HashMap<String, ModelPrice>,
+}
+
+#[derive(Clone, Copy, Serialize, Deserialize, Debug, Default)]
+pub struct ModelPrice {
+    pub prompt: f64,
+    pub completion: f64,
+}
+
+impl UsageConfig {
+    pub fn cost(&self, model: &str, prompt_tokens: i64, completion_tokens: i64) -> Option<f64> {
+        self.prices.get(model).map(|price| {
+            (prompt_tokens as f64 * price.prompt + completion_tokens as f64 * price.completion)
+                / 1_000_000.0
+        })
+    }
+}
+
+#[derive(Clone, Copy, Serialize, Deserialize, Debug, Default)]
+pub struct ModelParameters {
+    pub temperature: Option<f32>,
+    pub max_tokens: Option<u32>,
+    pub top_p: Option<f32>,
+}
+
+impl OpenAiConfig {
+    /// Whether the model accepts image input.
+    pub fn supports_vision(&self, model: &str) -> bool {
+        self.vision_models.iter().any(|vision| vision == model)
+    }
+
+    /// Default model when it accepts images, otherwise the first vision model.
+    pub fn vision_model(&self) -> Option<&str> {
+        if self.supports_vision(&self.model) {
+            Some(&self.model)
+        } else {
+            self.vision_models.first().map(String::as_str)
+        }
+    }
+
+    /// Models users can pick from, the default model is always included.
+    pub fn allowed_models(&self) -> Vec<String> {
+        let mut models = self.models.clone();
+
+        if !models.contains(&self.model) {
+            models.insert(0, self.model.clone());
+        }
+
+        models
+    }
+
+    pub fn compare_models(&self) -> Vec<String> {
+        if self.compare_models.is_empty() {
+            self.allowed_models()
+        } else {
+            self.compare_models.clone()
+        }
+    }
+
+    /// Returns the selected model if it is still allowed, the default model otherwise.
+    pub fn resolve_model(&self, selected: Option<&str>) -> String {
+        selected
+            .filter(|model| self.allowed_models().iter().any(|allowed| allowed == model))
+            .unwrap_or(&self.model)
+            .to_string()
+    }
+
+    /// Global sampling parameters overridden by the model specific ones.
+    pub fn parameters(&self, model: &str) -> ModelParameters {
+        let overrides = self.parameters.get(model).copied().unwrap_or_default();
+
+        ModelParameters {
+            temperature: overrides.temperature.or(self.temperature),
+            max_tokens: overrides.max_tokens.or(self.max_tokens),
+            top_p: overrides.top_p.or(self.top_p),
+        }
+    }
+
+    pub fn context_budget(&self, model: &str) -> usize {
+        self.context_budgets
+            .get(model)
+            .copied()
+            .unwrap_or(self.default_context_budget)
+    }
 }
 
 #[derive(Clone, Serialize, Deserialize, Debug)]
@@ -1,13 +1,26 @@
-use sqlx::SqlitePool;
+use std::{str::FromStr, time::Duration};
+
+use sqlx::{
+    sqlite::{SqliteConnectOptions, SqliteJournalMode},
+    SqlitePool,
+};
 
 use crate::{
     env::ENV,
-    types::databases::{CategoriesDb, Database, TransactionsDb, UsersDb},
+    types::databases::{
+        CategoriesDb, ChatMessagesDb, ComparisonsDb, ConversationsDb, Database, DocumentsDb,
+        JobsDb, MemoriesDb, PersonasDb, TransactionsDb, UsageDb, UsersDb,
+    },
 };
 
 impl Database {
     pub async fn new() -> Self {
-        let pool = SqlitePool::connect(&ENV.database_url)
+        let options = SqliteConnectOptions::from_str(&ENV.database_url)
+            .expect("Invalid database url")
+            .journal_mode(SqliteJournalMode::Wal)
+            .busy_timeout(Duration::from_secs(5));
+
+        let pool = SqlitePool::connect_with(options)
             .await
             .expect("Failed to connect to database");
 
@@ -19,6 +32,23 @@ impl Database {
         Self { pool }
     }
 
+    /// Private in-memory database with all migrations applied, for tests.
+    #[cfg(test)]
+    pub async fn in_memory() -> Self {
+        let pool = sqlx::sqlite::SqlitePoolOptions::new()
+            .max_connections(1)
+            .connect("sqlite::memory:")
+            .await
+            .expect("Failed to open in-memory database");
+
+        sqlx::migrate!("./migrations")
+            .run(&pool)
+            .await
+            .expect("Failed to run migrations");
+
+        Self { pool }
+    }
+
     pub fn users(&self) -> UsersDb {
         UsersDb::new(&self.pool)
     }
@@ -30,4 +60,36 @@ impl Database {
     pub fn transactions(&self) -> TransactionsDb {
         TransactionsDb::new(&self.pool)
     }
+
+    pub fn chat_messages(&self) -> ChatMessagesDb {
+        ChatMessagesDb::new(&self.pool)
+    }
+
+    pub fn conversations(&self) -> ConversationsDb {
+        ConversationsDb::new(&self.pool)
+    }
+
+    pub fn usage(&self) -> UsageDb {
+        UsageDb::new(&self.pool)
+    }
+
+    pub fn personas(&self) -> PersonasDb {
+        PersonasDb::new(&self.pool)
+    }
+
+    pub fn documents(&self) -> DocumentsDb {
+        DocumentsDb::new(&self.pool)
+    }
+
+    pub fn memories(&self) -> MemoriesDb {
+        MemoriesDb::new(&self.pool)
+    }
+
+    pub fn jobs(&self) -> JobsDb {
+        JobsDb::new(&self.pool)
+    }
+
+    pub fn comparisons(&self) -> ComparisonsDb {
+        ComparisonsDb::new(&self.pool)
+    }
 }
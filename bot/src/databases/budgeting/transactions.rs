@@ -14,14 +14,30 @@ impl TransactionsDb {
     ) {
         let now = chrono::Utc::now().timestamp();
 
+        self.add_at(amount, description, user_id, category_id, now, None)
+            .await;
+    }
+
+    /// Adds a transaction dated at the `date` unix timestamp, optionally linked to the
+    /// Telegram file id of its receipt photo.
+    pub async fn add_at(
+        &self,
+        amount: i64,
+        description: Option<String>,
+        user_id: i64,
+        category_id: i64,
+        date: i64,
+        receipt_file_id: Option<&str>,
+    ) {
         sqlx::query!(
-            "INSERT INTO transactions (amount, description, user_id, category_id, date)
-             VALUES (?, ?, ?, ?, ?)",
+            "INSERT INTO transactions (amount, description, user_id, category_id, date, receipt_file_id)
+             VALUES (?, ?, ?, ?, ?, ?)",
             amount,
             description,
             user_id,
             category_id,
-            now
+            date,
+            receipt_file_id
         )
         .execute(&self.pool)
         .await
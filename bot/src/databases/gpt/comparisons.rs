@@ -0,0 +1,116 @@
+use crate::types::{databases::ComparisonsDb, models::ComparisonAnswerRow};
+
+impl ComparisonsDb {
+    /// `last_message_id` is the latest message of the conversation the answers follow.
+    pub async fn create(
+        &self,
+        chat_id: i64,
+        conversation_id: i64,
+        prompt: &str,
+        last_message_id: i64,
+    ) -> sqlx::Result<i64> {
+        let now = chrono::Utc::now().timestamp();
+
+        let result = sqlx::query!(
+            "INSERT INTO comparisons (chat_id, conversation_id, prompt, last_message_id, created_at)
+             VALUES (?, ?, ?, ?, ?)",
+            chat_id,
+            conversation_id,
+            prompt,
+            last_message_id,
+            now
+        )
+        .execute(&self.pool)
+        .await?;
+
+        Ok(result.last_insert_rowid())
+    }
+
+    /// Stores a finished answer, `message_ids` is a JSON array of the Telegram messages it was rendered into.
+    pub async fn add_answer(
+        &self,
+        comparison_id: i64,
+        model: &str,
+        content: &str,
+        message_ids: &str,
+    ) -> sqlx::Result<i64> {
+        let now = chrono::Utc::now().timestamp();
+
+        let result = sqlx::query!(
+            "INSERT INTO comparison_answers (comparison_id, model, content, message_ids, created_at)
+             VALUES (?, ?, ?, ?, ?)",
+            comparison_id,
+            model,
+            content,
+            message_ids,
+            now
+        )
+        .execute(&self.pool)
+        .await?;
+
+        Ok(result.last_insert_rowid())
+    }
+
+    pub async fn answer(&self, chat_id: i64, id: i64) -> sqlx::Result<Option<ComparisonAnswerRow>> {
+        sqlx::query_as!(
+            ComparisonAnswerRow,
+            r#"
+            SELECT
+                a.id AS "id!: i64",
+                a.comparison_id AS "comparison_id!: i64",
+                c.conversation_id AS "conversation_id!: i64",
+                c.prompt,
+                c.last_message_id,
+                c.picked_answer_id AS "picked_answer_id?: i64",
+                a.model,
+                a.content,
+                a.message_ids
+            FROM comparison_answers a
+            JOIN comparisons c ON c.id = a.comparison_id
+            WHERE c.chat_id = ? AND a.id = ?
+            "#,
+            chat_id,
+            id
+        )
+        .fetch_optional(&self.pool)
+        .await
+    }
+
+    pub async fn answers(&self, comparison_id: i64) -> sqlx::Result<Vec<ComparisonAnswerRow>> {
+        sqlx::query_as!(
+            ComparisonAnswerRow,
+            r#"
+            SELECT
+                a.id AS "id!: i64",
+                a.comparison_id AS "comparison_id!: i64",
+                c.conversation_id AS "conversation_id!: i64",
+                c.prompt,
+                c.last_message_id,
+                c.picked_answer_id AS "picked_answer_id?: i64",
+                a.model,
+                a.content,
+                a.message_ids
+            FROM comparison_answers a
+            JOIN comparisons c ON c.id = a.comparison_id
+            WHERE a.comparison_id = ?
+            ORDER BY a.id ASC
+            "#,
+            comparison_id
+        )
+        .fetch_all(&self.pool)
+        .await
+    }
+
+    /// Marks the answer as picked, false when another answer of the comparison was picked first.
+    pub async fn pick(&self, comparison_id: i64, answer_id: i64) -> sqlx::Result<bool> {
+        let result = sqlx::query!(
+            "UPDATE comparisons SET picked_answer_id = ? WHERE id = ? AND picked_answer_id IS NULL",
+            answer_id,
+            comparison_id
+        )
+        .execute(&self.pool)
+        .await?;
+
+        Ok(result.rows_affected() > 0)
+    }
+}
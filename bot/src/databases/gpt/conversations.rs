@@ -0,0 +1,269 @@
+use crate::types::{common::AppError, databases::ConversationsDb, models::ConversationRow};
+
+const DEFAULT_CONVERSATION_NAME: &str = "default";
+
+/// Empty names are refused, numeric ones would be ambiguous with conversation ids in
+/// `/switch` and `/drop`.
+pub fn validate_name(name: &str) -> Result<(), AppError> {
+    if name.trim().is_empty() {
+        return Err(AppError::InternalError(
+            "Conversation name cannot be empty.".to_string(),
+        ));
+    }
+
+    if name.trim().parse::<i64>().is_ok() {
+        return Err(AppError::InternalError(format!(
+            "Conversation name '{}' cannot be a number.",
+            name.trim()
+        )));
+    }
+
+    Ok(())
+}
+
+fn timestamp_to_naive(ts: i64) -> chrono::NaiveDateTime {
+    chrono::DateTime::from_timestamp(ts, 0)
+        .map(|dt| dt.naive_utc())
+        .unwrap_or_default()
+}
+
+impl ConversationsDb {
+    pub async fn create(&self, chat_id: i64, name: &str) -> Result<i64, AppError> {
+        validate_name(name)?;
+
+        let now = chrono::Utc::now().timestamp();
+
+        let result = sqlx::query!(
+            "INSERT OR IGNORE INTO conversations (chat_id, name, created_at) VALUES (?, ?, ?)",
+            chat_id,
+            name,
+            now
+        )
+        .execute(&self.pool)
+        .await?;
+
+        if result.rows_affected() == 0 {
+            return Err(AppError::InternalError(format!(
+                "Conversation '{}' already exists.",
+                name
+            )));
+        }
+
+        Ok(result.last_insert_rowid())
+    }
+
+    pub async fn list(&self, chat_id: i64) -> sqlx::Result<Vec<ConversationRow>> {
+        let rows = sqlx::query!(
+            r#"
+            SELECT
+                c.id AS "id!: i64",
+                c.chat_id,
+                c.name,
+                c.model,
+                c.parent_id,
+                c.created_at,
+                COUNT(m.id) AS "messages_count!: i64"
+            FROM conversations c
+            LEFT JOIN chat_messages m ON m.conversation_id = c.id AND m.role != 'system'
+            WHERE c.chat_id = ?
+            GROUP BY c.id
+            ORDER BY c.id ASC
+            "#,
+            chat_id
+        )
+        .fetch_all(&self.pool)
+        .await?;
+
+        Ok(rows
+            .into_iter()
+            .map(|row| ConversationRow {
+                id: row.id,
+                chat_id: row.chat_id,
+                name: row.name,
+                model: row.model,
+                parent_id: row.parent_id,
+                created_at: timestamp_to_naive(row.created_at),
+                messages_count: row.messages_count,
+            })
+            .collect())
+    }
+
+    /// Finds a conversation of the chat by user input, an id takes precedence over a name.
+    pub async fn find(&self, chat_id: i64, key: &str) -> sqlx::Result<Option<ConversationRow>> {
+        let key = key.trim();
+
+        if let Ok(id) = key.parse::<i64>() {
+            if let Some(conversation) = self.find_by_id(chat_id, id).await? {
+                return Ok(Some(conversation));
+            }
+        }
+
+        self.find_by_name(chat_id, key).await
+    }
+
+    pub async fn find_by_id(&self, chat_id: i64, id: i64) -> sqlx::Result<Option<ConversationRow>> {
+        Ok(self
+            .list(chat_id)
+            .await?
+            .into_iter()
+            .find(|conversation| conversation.id == id))
+    }
+
+    pub async fn find_by_name(
+        &self,
+        chat_id: i64,
+        name: &str,
+    ) -> sqlx::Result<Option<ConversationRow>> {
+        Ok(self
+            .list(chat_id)
+            .await?
+            .into_iter()
+            .find(|conversation| conversation.name == name))
+    }
+
+    pub async fn rename(&self, id: i64, name: &str) -> Result<(), AppError> {
+        validate_name(name)?;
+
+        let result = sqlx::query!(
+            "UPDATE OR IGNORE conversations SET name = ? WHERE id = ?",
+            name,
+            id
+        )
+        .execute(&self.pool)
+        .await?;
+
+        if result.rows_affected() == 0 {
+            let exists = sqlx::query!("SELECT id FROM conversations WHERE id = ?", id)
+                .fetch_optional(&self.pool)
+                .await?
+                .is_some();
+
+            return Err(AppError::InternalError(if exists {
+                format!("Conversation '{}' already exists.", name)
+            } else {
+                format!("Conversation {} not found.", id)
+            }));
+        }
+
+        Ok(())
+    }
+
+    pub async fn set_model(&self, id: i64, model: &str) -> sqlx::Result<()> {
+        sqlx::query!("UPDATE conversations SET model = ? WHERE id = ?", model, id)
+            .execute(&self.pool)
+            .await?;
+
+        Ok(())
+    }
+
+    /// Creates a branch of `parent` holding its system prompt, summary and the turns up to
+    /// and including `until_id`.
+    pub async fn fork(
+        &self,
+        chat_id: i64,
+        parent: &ConversationRow,
+        name: &str,
+        until_id: i64,
+    ) -> sqlx::Result<i64> {
+        let now = chrono::Utc::now().timestamp();
+        let mut transaction = self.pool.begin().await?;
+
+        let id = sqlx::query!(
+            "INSERT INTO conversations (chat_id, name, model, parent_id, created_at)
+             VALUES (?, ?, ?, ?, ?)",
+            chat_id,
+            name,
+            parent.model,
+            parent.id,
+            now
+        )
+        .execute(&mut *transaction)
+        .await?
+        .last_insert_rowid();
+
+        sqlx::query!(
+            "INSERT INTO chat_messages
+                (chat_id, conversation_id, role, content, tokens, kind, tool_calls, tool_call_id, image, created_at)
+             SELECT chat_id, ?, role, content, tokens, kind, tool_calls, tool_call_id, image, created_at
+             FROM chat_messages
+             WHERE conversation_id = ? AND (role = 'system' OR id <= ?)
+             ORDER BY id ASC",
+            id,
+            parent.id,
+            until_id
+        )
+        .execute(&mut *transaction)
+        .await?;
+
+        transaction.commit().await?;
+
+        Ok(id)
+    }
+
+    pub async fn delete(&self, id: i64) -> sqlx::Result<bool> {
+        let mut transaction = self.pool.begin().await?;
+
+        sqlx::query!("DELETE FROM chat_messages WHERE conversation_id = ?", id)
+            .execute(&mut *transaction)
+            .await?;
+
+        let result = sqlx::query!("DELETE FROM conversations WHERE id = ?", id)
+            .execute(&mut *transaction)
+            .await?;
+
+        transaction.commit().await?;
+
+        Ok(result.rows_affected() > 0)
+    }
+
+    pub async fn set_active(&self, chat_id: i64, conversation_id: i64) -> sqlx::Result<()> {
+        sqlx::query!(
+            "INSERT INTO chat_settings (chat_id, active_conversation_id) VALUES (?, ?)
+             ON CONFLICT(chat_id) DO UPDATE SET active_conversation_id = excluded.active_conversation_id",
+            chat_id,
+            conversation_id
+        )
+        .execute(&self.pool)
+        .await?;
+
+        Ok(())
+    }
+
+    /// Returns the active conversation of the chat, falling back to the latest one
+    /// or a freshly created default conversation.
+    pub async fn active(&self, chat_id: i64) -> Result<ConversationRow, AppError> {
+        let active_id = sqlx::query!(
+            "SELECT active_conversation_id FROM chat_settings WHERE chat_id = ?",
+            chat_id
+        )
+        .fetch_optional(&self.pool)
+        .await?
+        .and_then(|row| row.active_conversation_id);
+
+        let conversations = self.list(chat_id).await?;
+
+        if let Some(active) = conversations
+            .iter()
+            .find(|conversation| Some(conversation.id) == active_id)
+        {
+            return Ok(active.clone());
+        }
+
+        let fallback = match conversations.into_iter().last() {
+            Some(conversation) => conversation,
+            None => {
+                self.create(chat_id, DEFAULT_CONVERSATION_NAME).await?;
+
+                self.find_by_name(chat_id, DEFAULT_CONVERSATION_NAME)
+                    .await?
+                    .ok_or_else(|| {
+                        AppError::InternalError("Default conversation not created.".into())
+                    })?
+            }
+        };
+
+        self.set_active(chat_id, fallback.id).await?;
+
+        Ok(fallback)
+    }
+}
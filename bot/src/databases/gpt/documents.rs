@@ -0,0 +1,123 @@
+use crate::{
+    types::{
+        databases::DocumentsDb,
+        models::{DocumentChunkRow, DocumentRow},
+    },
+    utils::embeddings::encode_vector,
+};
+
+impl DocumentsDb {
+    /// Stores a document with its chunks and their embeddings in one transaction.
+    pub async fn add(
+        &self,
+        chat_id: i64,
+        name: &str,
+        chunks: &[String],
+        embeddings: &[Vec<f32>],
+    ) -> sqlx::Result<i64> {
+        let now = chrono::Utc::now().timestamp();
+        let mut transaction = self.pool.begin().await?;
+
+        let id = sqlx::query!(
+            "INSERT INTO documents (chat_id, name, created_at) VALUES (?, ?, ?)",
+            chat_id,
+            name,
+            now
+        )
+        .execute(&mut *transaction)
+        .await?
+        .last_insert_rowid();
+
+        for (position, (content, embedding)) in chunks.iter().zip(embeddings).enumerate() {
+            let position = position as i64;
+            let embedding = encode_vector(embedding);
+
+            sqlx::query!(
+                "INSERT INTO document_chunks (document_id, position, content, embedding)
+                 VALUES (?, ?, ?, ?)",
+                id,
+                position,
+                content,
+                embedding
+            )
+            .execute(&mut *transaction)
+            .await?;
+        }
+
+        transaction.commit().await?;
+
+        Ok(id)
+    }
+
+    pub async fn list(&self, chat_id: i64) -> sqlx::Result<Vec<DocumentRow>> {
+        let rows = sqlx::query!(
+            r#"
+            SELECT
+                d.id AS "id!: i64",
+                d.name,
+                d.created_at,
+                COUNT(c.id) AS "chunks!: i64"
+            FROM documents d
+            LEFT JOIN document_chunks c ON c.document_id = d.id
+            WHERE d.chat_id = ?
+            GROUP BY d.id
+            ORDER BY d.id ASC
+            "#,
+            chat_id
+        )
+        .fetch_all(&self.pool)
+        .await?;
+
+        Ok(rows
+            .into_iter()
+            .map(|row| DocumentRow {
+                id: row.id,
+                name: row.name,
+                chunks: row.chunks,
+                created_at: chrono::DateTime::from_timestamp(row.created_at, 0)
+                    .map(|dt| dt.naive_utc())
+                    .unwrap_or_default(),
+            })
+            .collect())
+    }
+
+    /// Every chunk of the chat's documents, scored in memory by the caller.
+    pub async fn chunks(&self, chat_id: i64) -> sqlx::Result<Vec<DocumentChunkRow>> {
+        sqlx::query_as!(
+            DocumentChunkRow,
+            r#"
+            SELECT
+                d.name AS document_name,
+                c.position,
+                c.content,
+                c.embedding
+            FROM document_chunks c
+            JOIN documents d ON d.id = c.document_id
+            WHERE d.chat_id = ?
+            "#,
+            chat_id
+        )
+        .fetch_all(&self.pool)
+        .await
+    }
+
+    pub async fn delete(&self, chat_id: i64, id: i64) -> sqlx::Result<bool> {
+        let mut transaction = self.pool.begin().await?;
+
+        let result = sqlx::query!(
+            "DELETE FROM documents WHERE chat_id = ? AND id = ?",
+            chat_id,
+            id
+        )
+        .execute(&mut *transaction)
+        .await?;
+
+        sqlx::query!("DELETE FROM document_chunks WHERE document_id = ?", id)
+            .execute(&mut *transaction)
+            .await?;
+
+        transaction.commit().await?;
+
+        Ok(result.rows_affected() > 0)
+    }
+}
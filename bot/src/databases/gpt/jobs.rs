@@ -0,0 +1,97 @@
+use crate::types::{databases::JobsDb, models::JobRow};
+
+impl JobsDb {
+    pub async fn add(
+        &self,
+        chat_id: i64,
+        schedule: &str,
+        prompt: &str,
+        next_run: i64,
+    ) -> sqlx::Result<i64> {
+        let now = chrono::Utc::now().timestamp();
+
+        let result = sqlx::query!(
+            "INSERT INTO jobs (chat_id, schedule, prompt, next_run, created_at) VALUES (?, ?, ?, ?, ?)",
+            chat_id,
+            schedule,
+            prompt,
+            next_run,
+            now
+        )
+        .execute(&self.pool)
+        .await?;
+
+        Ok(result.last_insert_rowid())
+    }
+
+    pub async fn list(&self, chat_id: i64) -> sqlx::Result<Vec<JobRow>> {
+        sqlx::query_as!(
+            JobRow,
+            r#"
+            SELECT id AS "id!: i64", chat_id, schedule, prompt, next_run
+            FROM jobs
+            WHERE chat_id = ?
+            ORDER BY id ASC
+            "#,
+            chat_id
+        )
+        .fetch_all(&self.pool)
+        .await
+    }
+
+    /// Jobs of every chat whose next run is at or before the `now` unix timestamp.
+    pub async fn due(&self, now: i64) -> sqlx::Result<Vec<JobRow>> {
+        sqlx::query_as!(
+            JobRow,
+            r#"
+            SELECT id AS "id!: i64", chat_id, schedule, prompt, next_run
+            FROM jobs
+            WHERE next_run <= ?
+            ORDER BY next_run ASC
+            "#,
+            now
+        )
+        .fetch_all(&self.pool)
+        .await
+    }
+
+    pub async fn set_next_run(&self, id: i64, next_run: i64) -> sqlx::Result<()> {
+        sqlx::query!("UPDATE jobs SET next_run = ? WHERE id = ?", next_run, id)
+            .execute(&self.pool)
+            .await?;
+
+        Ok(())
+    }
+
+    pub async fn delete(&self, chat_id: i64, id: i64) -> sqlx::Result<bool> {
+        let result = sqlx::query!("DELETE FROM jobs WHERE chat_id = ? AND id = ?", chat_id, id)
+            .execute(&self.pool)
+            .await?;
+
+        Ok(result.rows_affected() > 0)
+    }
+
+    pub async fn timezone(&self, chat_id: i64) -> sqlx::Result<Option<String>> {
+        let row = sqlx::query!(
+            "SELECT timezone FROM chat_settings WHERE chat_id = ?",
+            chat_id
+        )
+        .fetch_optional(&self.pool)
+        .await?;
+
+        Ok(row.and_then(|row| row.timezone))
+    }
+
+    pub async fn set_timezone(&self, chat_id: i64, timezone: &str) -> sqlx::Result<()> {
+        sqlx::query!(
+            "INSERT INTO chat_settings (chat_id, timezone) VALUES (?, ?)
+             ON CONFLICT(chat_id) DO UPDATE SET timezone = excluded.timezone",
+            chat_id,
+            timezone
+        )
+        .execute(&self.pool)
+        .await?;
+
+        Ok(())
+    }
+}
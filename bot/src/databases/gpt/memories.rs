@@ -0,0 +1,88 @@
+use crate::types::{databases::MemoriesDb, models::MemoryRow};
+
+impl MemoriesDb {
+    /// Stores a memory, `pending` ones are proposed by the AI and wait for confirmation.
+    pub async fn add(&self, chat_id: i64, content: &str, pending: bool) -> sqlx::Result<i64> {
+        let now = chrono::Utc::now().timestamp();
+
+        let result = sqlx::query!(
+            "INSERT INTO memories (chat_id, content, pending, created_at) VALUES (?, ?, ?, ?)",
+            chat_id,
+            content,
+            pending,
+            now
+        )
+        .execute(&self.pool)
+        .await?;
+
+        Ok(result.last_insert_rowid())
+    }
+
+    /// Confirmed memories of the chat, oldest first.
+    pub async fn list(&self, chat_id: i64) -> sqlx::Result<Vec<MemoryRow>> {
+        sqlx::query_as!(
+            MemoryRow,
+            r#"
+            SELECT id AS "id!: i64", content
+            FROM memories
+            WHERE chat_id = ? AND pending = 0
+            ORDER BY id ASC
+            "#,
+            chat_id
+        )
+        .fetch_all(&self.pool)
+        .await
+    }
+
+    pub async fn get(&self, chat_id: i64, id: i64) -> sqlx::Result<Option<MemoryRow>> {
+        sqlx::query_as!(
+            MemoryRow,
+            r#"
+            SELECT id AS "id!: i64", content
+            FROM memories
+            WHERE chat_id = ? AND id = ?
+            "#,
+            chat_id,
+            id
+        )
+        .fetch_optional(&self.pool)
+        .await
+    }
+
+    pub async fn confirm(&self, chat_id: i64, id: i64) -> sqlx::Result<bool> {
+        let result = sqlx::query!(
+            "UPDATE memories SET pending = 0 WHERE chat_id = ? AND id = ?",
+            chat_id,
+            id
+        )
+        .execute(&self.pool)
+        .await?;
+
+        Ok(result.rows_affected() > 0)
+    }
+
+    /// Deletes a proposal still waiting for confirmation, confirmed memories are kept.
+    pub async fn reject(&self, chat_id: i64, id: i64) -> sqlx::Result<bool> {
+        let result = sqlx::query!(
+            "DELETE FROM memories WHERE chat_id = ? AND id = ? AND pending = 1",
+            chat_id,
+            id
+        )
+        .execute(&self.pool)
+        .await?;
+
+        Ok(result.rows_affected() > 0)
+    }
+
+    pub async fn delete(&self, chat_id: i64, id: i64) -> sqlx::Result<bool> {
+        let result = sqlx::query!(
+            "DELETE FROM memories WHERE chat_id = ? AND id = ?",
+            chat_id,
+            id
+        )
+        .execute(&self.pool)
+        .await?;
+
+        Ok(result.rows_affected() > 0)
+    }
+}
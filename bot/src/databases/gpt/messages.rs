@@ -0,0 +1,380 @@
+use crate::types::{
+    chat::{ChatRole, ExportedMessage, ToolCall},
+    databases::ChatMessagesDb,
+    models::{ChatMessageRow, MessageLinkRow},
+};
+
+impl ChatMessagesDb {
+    pub async fn list(&self, conversation_id: i64) -> sqlx::Result<Vec<ChatMessageRow>> {
+        let rows = sqlx::query!(
+            r#"
+            SELECT
+                id AS "id!: i64",
+                chat_id,
+                conversation_id,
+                role,
+                content,
+                tokens,
+                kind,
+                tool_calls,
+                tool_call_id,
+                image,
+                created_at
+            FROM chat_messages
+            WHERE conversation_id = ?
+            ORDER BY id ASC
+            "#,
+            conversation_id
+        )
+        .fetch_all(&self.pool)
+        .await?;
+
+        Ok(rows
+            .into_iter()
+            .map(|row| {
+                let created_at = chrono::DateTime::from_timestamp(row.created_at, 0)
+                    .map(|dt| dt.naive_utc())
+                    .unwrap_or_default();
+
+                ChatMessageRow {
+                    id: row.id,
+                    chat_id: row.chat_id,
+                    conversation_id: row.conversation_id,
+                    role: row.role,
+                    content: row.content,
+                    tokens: row.tokens,
+                    kind: row.kind,
+                    tool_calls: row.tool_calls,
+                    tool_call_id: row.tool_call_id,
+                    image: row.image,
+                    created_at,
+                }
+            })
+            .collect())
+    }
+
+    pub async fn add(
+        &self,
+        chat_id: i64,
+        conversation_id: i64,
+        role: ChatRole,
+        content: &str,
+        tokens: usize,
+    ) -> sqlx::Result<i64> {
+        let now = chrono::Utc::now().timestamp();
+        let role: &str = role.into();
+        let tokens = tokens as i64;
+
+        let result = sqlx::query!(
+            "INSERT INTO chat_messages (chat_id, conversation_id, role, content, tokens, created_at)
+             VALUES (?, ?, ?, ?, ?, ?)",
+            chat_id,
+            conversation_id,
+            role,
+            content,
+            tokens,
+            now
+        )
+        .execute(&self.pool)
+        .await?;
+
+        Ok(result.last_insert_rowid())
+    }
+
+    /// Stores a user turn with an image, `image` is a data URL and `content` the optional caption.
+    pub async fn add_image(
+        &self,
+        chat_id: i64,
+        conversation_id: i64,
+        content: &str,
+        image: &str,
+        tokens: usize,
+    ) -> sqlx::Result<i64> {
+        let now = chrono::Utc::now().timestamp();
+        let tokens = tokens as i64;
+
+        let result = sqlx::query!(
+            "INSERT INTO chat_messages (chat_id, conversation_id, role, content, tokens, image, created_at)
+             VALUES (?, ?, 'user', ?, ?, ?, ?)",
+            chat_id,
+            conversation_id,
+            content,
+            tokens,
+            image,
+            now
+        )
+        .execute(&self.pool)
+        .await?;
+
+        Ok(result.last_insert_rowid())
+    }
+
+    /// Stores an assistant turn requesting tool calls, `tool_calls` is the serialized call list.
+    pub async fn add_tool_calls(
+        &self,
+        chat_id: i64,
+        conversation_id: i64,
+        content: &str,
+        tool_calls: &str,
+        tokens: usize,
+    ) -> sqlx::Result<i64> {
+        let now = chrono::Utc::now().timestamp();
+        let tokens = tokens as i64;
+
+        let result = sqlx::query!(
+            "INSERT INTO chat_messages (chat_id, conversation_id, role, content, tokens, tool_calls, created_at)
+             VALUES (?, ?, 'assistant', ?, ?, ?, ?)",
+            chat_id,
+            conversation_id,
+            content,
+            tokens,
+            tool_calls,
+            now
+        )
+        .execute(&self.pool)
+        .await?;
+
+        Ok(result.last_insert_rowid())
+    }
+
+    pub async fn add_tool_result(
+        &self,
+        chat_id: i64,
+        conversation_id: i64,
+        tool_call_id: &str,
+        content: &str,
+        tokens: usize,
+    ) -> sqlx::Result<i64> {
+        let now = chrono::Utc::now().timestamp();
+        let tokens = tokens as i64;
+
+        let result = sqlx::query!(
+            "INSERT INTO chat_messages (chat_id, conversation_id, role, content, tokens, tool_call_id, created_at)
+             VALUES (?, ?, 'tool', ?, ?, ?, ?)",
+            chat_id,
+            conversation_id,
+            content,
+            tokens,
+            tool_call_id,
+            now
+        )
+        .execute(&self.pool)
+        .await?;
+
+        Ok(result.last_insert_rowid())
+    }
+
+    /// Removes the assistant and tool turns answering the latest user turn.
+    pub async fn delete_last_answer(&self, conversation_id: i64) -> sqlx::Result<u64> {
+        let result = sqlx::query!(
+            "DELETE FROM chat_messages
+             WHERE conversation_id = ?
+               AND role IN ('assistant', 'tool')
+               AND id > (
+                   SELECT COALESCE(MAX(id), 0) FROM chat_messages
+                   WHERE conversation_id = ? AND role = 'user'
+               )",
+            conversation_id,
+            conversation_id
+        )
+        .execute(&self.pool)
+        .await?;
+
+        Ok(result.rows_affected())
+    }
+
+    /// Removes every turn and summary except the system prompt.
+    pub async fn clear(&self, conversation_id: i64) -> sqlx::Result<u64> {
+        let result = sqlx::query!(
+            "DELETE FROM chat_messages
+             WHERE conversation_id = ? AND (role != 'system' OR kind = 'summary')",
+            conversation_id
+        )
+        .execute(&self.pool)
+        .await?;
+
+        Ok(result.rows_affected())
+    }
+
+    /// Inserts exported messages into a conversation in one transaction, keeping their order
+    /// and timestamps.
+    pub async fn import(
+        &self,
+        chat_id: i64,
+        conversation_id: i64,
+        messages: &[ExportedMessage],
+    ) -> sqlx::Result<()> {
+        let mut transaction = self.pool.begin().await?;
+
+        for message in messages {
+            let created_at = message.created_at.and_utc().timestamp();
+            let tool_calls = if message.tool_calls.is_empty() {
+                None
+            } else {
+                serde_json::to_string::<[ToolCall]>(&message.tool_calls).ok()
+            };
+
+            sqlx::query!(
+                "INSERT INTO chat_messages
+                    (chat_id, conversation_id, role, content, tokens, kind, tool_calls, tool_call_id, image, created_at)
+                 VALUES (?, ?, ?, ?, ?, ?, ?, ?, ?, ?)",
+                chat_id,
+                conversation_id,
+                message.role,
+                message.content,
+                message.tokens,
+                message.kind,
+                tool_calls,
+                message.tool_call_id,
+                message.image,
+                created_at
+            )
+            .execute(&mut *transaction)
+            .await?;
+        }
+
+        transaction.commit().await?;
+
+        Ok(())
+    }
+
+    /// Replaces the system prompt of a conversation in one transaction.
+    pub async fn set_system(
+        &self,
+        chat_id: i64,
+        conversation_id: i64,
+        content: &str,
+        tokens: usize,
+    ) -> sqlx::Result<()> {
+        let now = chrono::Utc::now().timestamp();
+        let tokens = tokens as i64;
+        let mut transaction = self.pool.begin().await?;
+
+        sqlx::query!(
+            "DELETE FROM chat_messages
+             WHERE conversation_id = ? AND role = 'system' AND kind = 'message'",
+            conversation_id
+        )
+        .execute(&mut *transaction)
+        .await?;
+
+        sqlx::query!(
+            "INSERT INTO chat_messages (chat_id, conversation_id, role, content, tokens, created_at)
+             VALUES (?, ?, 'system', ?, ?, ?)",
+            chat_id,
+            conversation_id,
+            content,
+            tokens,
+            now
+        )
+        .execute(&mut *transaction)
+        .await?;
+
+        transaction.commit().await?;
+
+        Ok(())
+    }
+
+    /// Replaces collapsed turns and the previous summary with a new summary in one transaction.
+    pub async fn summarize(
+        &self,
+        chat_id: i64,
+        conversation_id: i64,
+        collapsed_ids: &[i64],
+        summary: &str,
+        tokens: usize,
+    ) -> sqlx::Result<i64> {
+        let now = chrono::Utc::now().timestamp();
+        let tokens = tokens as i64;
+        let mut transaction = self.pool.begin().await?;
+
+        sqlx::query!(
+            "DELETE FROM chat_messages WHERE conversation_id = ? AND kind = 'summary'",
+            conversation_id
+        )
+        .execute(&mut *transaction)
+        .await?;
+
+        for id in collapsed_ids {
+            sqlx::query!("DELETE FROM chat_messages WHERE id = ?", id)
+                .execute(&mut *transaction)
+                .await?;
+        }
+
+        let result = sqlx::query!(
+            "INSERT INTO chat_messages (chat_id, conversation_id, role, content, tokens, kind, created_at)
+             VALUES (?, ?, 'system', ?, ?, 'summary', ?)",
+            chat_id,
+            conversation_id,
+            summary,
+            tokens,
+            now
+        )
+        .execute(&mut *transaction)
+        .await?;
+
+        transaction.commit().await?;
+
+        Ok(result.last_insert_rowid())
+    }
+
+    /// Remembers the Telegram messages a turn was rendered into, so replies can find it.
+    pub async fn link(
+        &self,
+        chat_id: i64,
+        telegram_message_ids: &[i32],
+        chat_message_id: i64,
+    ) -> sqlx::Result<()> {
+        for telegram_message_id in telegram_message_ids {
+            sqlx::query!(
+                "INSERT OR REPLACE INTO telegram_messages (chat_id, telegram_message_id, chat_message_id)
+                 VALUES (?, ?, ?)",
+                chat_id,
+                telegram_message_id,
+                chat_message_id
+            )
+            .execute(&self.pool)
+            .await?;
+        }
+
+        Ok(())
+    }
+
+    /// Finds the turn a Telegram message was rendered from, if the turn still exists.
+    pub async fn find_linked(
+        &self,
+        chat_id: i64,
+        telegram_message_id: i32,
+    ) -> sqlx::Result<Option<MessageLinkRow>> {
+        sqlx::query_as!(
+            MessageLinkRow,
+            r#"
+            SELECT m.id AS "chat_message_id!: i64", m.conversation_id
+            FROM telegram_messages t
+            JOIN chat_messages m ON m.id = t.chat_message_id
+            WHERE t.chat_id = ? AND t.telegram_message_id = ?
+            "#,
+            chat_id,
+            telegram_message_id
+        )
+        .fetch_optional(&self.pool)
+        .await
+    }
+
+    /// Whether the conversation continued with user turns after the turn `id`.
+    pub async fn has_user_turns_after(&self, conversation_id: i64, id: i64) -> sqlx::Result<bool> {
+        let row = sqlx::query!(
+            r#"
+            SELECT COUNT(*) AS "count!: i64"
+            FROM chat_messages
+            WHERE conversation_id = ? AND role = 'user' AND id > ?
+            "#,
+            conversation_id,
+            id
+        )
+        .fetch_one(&self.pool)
+        .await?;
+
+        Ok(row.count > 0)
+    }
+}
@@ -0,0 +1,8 @@
+pub mod comparisons;
+pub mod conversations;
+pub mod documents;
+pub mod jobs;
+pub mod memories;
+pub mod messages;
+pub mod personas;
+pub mod usage;
@@ -0,0 +1,81 @@
+use crate::types::{common::AppError, databases::PersonasDb, models::PersonaRow};
+
+impl PersonasDb {
+    pub async fn create(&self, chat_id: i64, name: &str, prompt: &str) -> Result<i64, AppError> {
+        let now = chrono::Utc::now().timestamp();
+
+        let result = sqlx::query!(
+            "INSERT OR IGNORE INTO personas (chat_id, name, prompt, created_at) VALUES (?, ?, ?, ?)",
+            chat_id,
+            name,
+            prompt,
+            now
+        )
+        .execute(&self.pool)
+        .await?;
+
+        if result.rows_affected() == 0 {
+            return Err(AppError::InternalError(format!(
+                "Persona '{}' already exists.",
+                name
+            )));
+        }
+
+        Ok(result.last_insert_rowid())
+    }
+
+    pub async fn list(&self, chat_id: i64) -> sqlx::Result<Vec<PersonaRow>> {
+        sqlx::query_as!(
+            PersonaRow,
+            r#"
+            SELECT id AS "id!: i64", chat_id, name, prompt
+            FROM personas
+            WHERE chat_id = ?
+            ORDER BY name ASC
+            "#,
+            chat_id
+        )
+        .fetch_all(&self.pool)
+        .await
+    }
+
+    pub async fn get(&self, chat_id: i64, id: i64) -> sqlx::Result<Option<PersonaRow>> {
+        sqlx::query_as!(
+            PersonaRow,
+            r#"
+            SELECT id AS "id!: i64", chat_id, name, prompt
+            FROM personas
+            WHERE chat_id = ? AND id = ?
+            "#,
+            chat_id,
+            id
+        )
+        .fetch_optional(&self.pool)
+        .await
+    }
+
+    pub async fn set_prompt(&self, chat_id: i64, id: i64, prompt: &str) -> sqlx::Result<bool> {
+        let result = sqlx::query!(
+            "UPDATE personas SET prompt = ? WHERE chat_id = ? AND id = ?",
+            prompt,
+            chat_id,
+            id
+        )
+        .execute(&self.pool)
+        .await?;
+
+        Ok(result.rows_affected() > 0)
+    }
+
+    pub async fn delete(&self, chat_id: i64, id: i64) -> sqlx::Result<bool> {
+        let result = sqlx::query!(
+            "DELETE FROM personas WHERE chat_id = ? AND id = ?",
+            chat_id,
+            id
+        )
+        .execute(&self.pool)
+        .await?;
+
+        Ok(result.rows_affected() > 0)
+    }
+}
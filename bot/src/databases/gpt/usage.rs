@@ -0,0 +1,69 @@
+use crate::types::{databases::UsageDb, models::UsageTotalRow};
+
+impl UsageDb {
+    pub async fn add(
+        &self,
+        chat_id: i64,
+        model: &str,
+        prompt_tokens: u32,
+        completion_tokens: u32,
+        latency_ms: i64,
+    ) -> sqlx::Result<()> {
+        let now = chrono::Utc::now().timestamp();
+
+        sqlx::query!(
+            "INSERT INTO usage (chat_id, model, prompt_tokens, completion_tokens, latency_ms, created_at)
+             VALUES (?, ?, ?, ?, ?, ?)",
+            chat_id,
+            model,
+            prompt_tokens,
+            completion_tokens,
+            latency_ms,
+            now
+        )
+        .execute(&self.pool)
+        .await?;
+
+        Ok(())
+    }
+
+    /// Totals per model of usage recorded since the `since` unix timestamp.
+    pub async fn totals_since(&self, chat_id: i64, since: i64) -> sqlx::Result<Vec<UsageTotalRow>> {
+        let rows = sqlx::query_as!(
+            UsageTotalRow,
+            r#"
+            SELECT
+                model,
+                COUNT(*) AS "requests!: i64",
+                SUM(prompt_tokens) AS "prompt_tokens!: i64",
+                SUM(completion_tokens) AS "completion_tokens!: i64"
+            FROM usage
+            WHERE chat_id = ? AND created_at >= ?
+            GROUP BY model
+            ORDER BY model
+            "#,
+            chat_id,
+            since
+        )
+        .fetch_all(&self.pool)
+        .await?;
+
+        Ok(rows)
+    }
+
+    pub async fn tokens_since(&self, chat_id: i64, since: i64) -> sqlx::Result<i64> {
+        let row = sqlx::query!(
+            r#"
+            SELECT COALESCE(SUM(prompt_tokens + completion_tokens), 0) AS "tokens!: i64"
+            FROM usage
+            WHERE chat_id = ? AND created_at >= ?
+            "#,
+            chat_id,
+            since
+        )
+        .fetch_one(&self.pool)
+        .await?;
+
+        Ok(row.tokens)
+    }
+}
@@ -1,2 +1,3 @@
 pub mod budgeting;
+pub mod gpt;
 pub mod main;
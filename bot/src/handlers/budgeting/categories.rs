@@ -17,7 +17,7 @@ pub async fn list(bot: Bot, msg: Message, categories_db: &CategoriesDb) -> Handl
     for kind in kinds.iter() {
         let mut categories = categories_db.list(*kind).await;
 
-        categories.sort_by(|a, b| a.id.cmp(&b.id));
+        categories.sort_by_key(|a| a.id);
 
         let list_text = categories
             .iter()
@@ -0,0 +1,218 @@
+use chrono::{Datelike, Local};
+use std::{collections::BTreeMap, time::Instant};
+use teloxide::prelude::*;
+use tracing::{info, warn};
+
+use crate::{
+    config::CONFIG,
+    handlers::gpt::usage,
+    providers::{CompletionRequest, Provider},
+    types::{
+        common::{AppError, DateFilter, HandleResult},
+        databases::Database,
+        models::TransactionRow,
+    },
+    utils::statistics::amount_to_float,
+};
+use async_openai::types::{
+    ChatCompletionRequestSystemMessageArgs, ChatCompletionRequestUserMessageArgs,
+};
+use shared::{InsightsCategory, InsightsResponse, OverviewTransaction};
+
+const INSIGHTS_PROMPT: &str = "You are a personal finance assistant. Write a short monthly report \
+from the budgeting figures in JSON the user sends. The current month may still be in progress. \
+Compare income and spending with the previous month, name the categories that grew or shrank \
+the most with their amounts, point out notable large transactions and finish with one or two \
+concrete suggestions. Amounts are in euros, use plain text without tables.";
+
+/// Largest spending transactions of the month sent to the model.
+const LARGEST_TRANSACTIONS: usize = 5;
+
+fn totals(transactions: &[TransactionRow]) -> (i64, i64, BTreeMap<String, i64>) {
+    let mut income = 0;
+    let mut spending = 0;
+    let mut categories: BTreeMap<String, i64> = BTreeMap::new();
+
+    for tx in transactions {
+        if tx.amount > 0 {
+            income += tx.amount;
+        } else {
+            spending -= tx.amount;
+            *categories.entry(tx.category_name.clone()).or_default() -= tx.amount;
+        }
+    }
+
+    (income, spending, categories)
+}
+
+/// Spending per category of the current month against the previous one, without the report.
+fn figures(current: &[TransactionRow], previous: &[TransactionRow]) -> InsightsResponse {
+    let today = Local::now().date_naive();
+    let (income, spending, current_categories) = totals(current);
+    let (previous_income, previous_spending, previous_categories) = totals(previous);
+
+    let mut names: Vec<&String> = current_categories
+        .keys()
+        .chain(previous_categories.keys())
+        .collect();
+
+    names.sort();
+    names.dedup();
+
+    let mut categories: Vec<InsightsCategory> = names
+        .into_iter()
+        .map(|name| {
+            let amount = current_categories.get(name).copied().unwrap_or_default();
+            let previous_amount = previous_categories.get(name).copied().unwrap_or_default();
+
+            InsightsCategory {
+                name: name.clone(),
+                amount: amount_to_float(amount),
+                previous_amount: amount_to_float(previous_amount),
+                delta: amount_to_float(amount - previous_amount),
+            }
+        })
+        .collect();
+
+    categories.sort_by(|a, b| b.amount.total_cmp(&a.amount));
+
+    let mut spendings: Vec<&TransactionRow> = current.iter().filter(|tx| tx.amount < 0).collect();
+
+    spendings.sort_by_key(|tx| tx.amount);
+
+    let largest_transactions = spendings
+        .into_iter()
+        .take(LARGEST_TRANSACTIONS)
+        .map(|tx| OverviewTransaction {
+            id: tx.id,
+            amount: amount_to_float(tx.amount).abs(),
+            category: tx.category_name.clone(),
+            is_income: false,
+            date: tx.date,
+            description: tx.description.clone(),
+        })
+        .collect();
+
+    InsightsResponse {
+        currency: "EUR".to_string(),
+        year: today.year() as u32,
+        month: today.month(),
+        income: amount_to_float(income),
+        spending: amount_to_float(spending),
+        previous_income: amount_to_float(previous_income),
+        previous_spending: amount_to_float(previous_spending),
+        categories,
+        largest_transactions,
+        report: String::new(),
+    }
+}
+
+/// Builds the current month's figures without the report, `None` when the month has
+/// no transactions yet.
+pub async fn month_figures(db: &Database, user_id: i64) -> Option<InsightsResponse> {
+    let transactions_db = db.transactions();
+    let current = transactions_db
+        .list_filtered(user_id, DateFilter::CurrentMonth)
+        .await;
+
+    if current.is_empty() {
+        return None;
+    }
+
+    let previous = transactions_db
+        .list_filtered(user_id, DateFilter::LastMonth)
+        .await;
+
+    Some(figures(&current, &previous))
+}
+
+/// Asks the model for a report on the figures.
+pub async fn report(
+    provider: &Provider,
+    db: &Database,
+    user_id: i64,
+    insights: &InsightsResponse,
+) -> Result<String, AppError> {
+    let model = &CONFIG.open_ai.model;
+    let today = Local::now().date_naive();
+
+    let request = CompletionRequest {
+        model: model.clone(),
+        messages: vec![
+            ChatCompletionRequestSystemMessageArgs::default()
+                .content(format!(
+                    "{}\n\nToday is {}.",
+                    INSIGHTS_PROMPT,
+                    today.format("%Y-%m-%d")
+                ))
+                .build()?
+                .into(),
+            ChatCompletionRequestUserMessageArgs::default()
+                .content(serde_json::to_string(insights)?)
+                .build()?
+                .into(),
+        ],
+        parameters: CONFIG.open_ai.parameters(model),
+        tools: Vec::new(),
+        schema: None,
+    };
+
+    let messages = request.messages.clone();
+    let started = Instant::now();
+    let completion = provider.complete(request).await?;
+
+    usage::record(
+        &db.usage(),
+        ChatId(user_id),
+        model,
+        completion
+            .usage
+            .unwrap_or_else(|| usage::estimate(model, &messages, &completion.content)),
+        started,
+    )
+    .await;
+
+    Ok(completion.content.trim().to_string())
+}
+
+/// Builds the current month's figures and asks the model for a report on them,
+/// `None` when the month has no transactions yet.
+pub async fn generate(
+    provider: &Provider,
+    db: &Database,
+    user_id: i64,
+) -> Result<Option<InsightsResponse>, AppError> {
+    let Some(mut insights) = month_figures(db, user_id).await else {
+        return Ok(None);
+    };
+
+    insights.report = report(provider, db, user_id, &insights).await?;
+
+    Ok(Some(insights))
+}
+
+pub async fn send(bot: Bot, provider: Provider, db: &Database, chat_id: ChatId) -> HandleResult {
+    info!("Insights, user: {}", chat_id);
+
+    if !usage::check_quota(&bot, &db.usage(), chat_id).await? {
+        return Ok(());
+    }
+
+    let placeholder = bot
+        .send_message(chat_id, "🧠 Analysing this month...")
+        .await?;
+
+    let text = match generate(&provider, db, chat_id.0).await {
+        Ok(Some(insights)) => format!("🧠 Insights\n\n{}", insights.report),
+        Ok(None) => "No transactions found for this month.".to_string(),
+        Err(e) => {
+            warn!("Failed to generate insights: {:?}", e);
+
+            "⚠️ Failed to generate insights, try again later.".to_string()
+        }
+    };
+
+    bot.edit_message_text(chat_id, placeholder.id, text).await?;
+
+    Ok(())
+}
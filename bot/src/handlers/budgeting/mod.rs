@@ -1,4 +1,6 @@
 pub mod categories;
+pub mod insights;
+pub mod proposals;
 pub mod settings;
 pub mod statistics;
 pub mod transactions;
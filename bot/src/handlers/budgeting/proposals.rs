@@ -0,0 +1,504 @@
+use chrono::{NaiveDate, Utc};
+use serde::{de::DeserializeOwned, Deserialize};
+use serde_json::json;
+use std::{str::FromStr, sync::Arc, time::Instant};
+use teloxide::prelude::*;
+use tracing::{info, warn};
+
+use crate::{
+    config::{ModelParameters, CONFIG},
+    handlers::gpt::{jobs, usage},
+    keyboard::budgeting::transactions::create_transaction_proposal_keyboard,
+    providers::{CompletionRequest, Provider, ResponseSchema},
+    types::{
+        common::{
+            AppError, BotDialogue, DialogueState, HandleResult, TransactionKind,
+            TransactionProposal,
+        },
+        databases::Database,
+        models::CategoryRow,
+    },
+    utils::{
+        files::download_photo,
+        transactions::{format_transaction_amount, format_transaction_date},
+    },
+};
+use async_openai::types::{
+    ChatCompletionRequestMessageContentPartImageArgs, ChatCompletionRequestSystemMessageArgs,
+    ChatCompletionRequestUserMessageArgs, ChatCompletionRequestUserMessageContentPart,
+    ImageUrlArgs,
+};
+
+const PARSE_PROMPT: &str = "Extract one budgeting transaction from the user's message. \
+Reply with a JSON object only: {\"amount\": number, \"kind\": \"income\" | \"spending\", \
+\"category\": string, \"date\": \"YYYY-MM-DD\", \"description\": string}. \
+Pick the category from the list for the kind, resolve relative dates against today \
+and keep the description short, e.g. the shop or purpose.";
+
+const RECEIPT_PROMPT: &str = "Read the receipt on the photo. Report the total paid, the merchant, \
+the purchase date as YYYY-MM-DD (null when not printed) and the spending category from the list \
+that fits the purchase best.";
+
+#[derive(Deserialize)]
+struct ParsedReceipt {
+    total: f64,
+    merchant: String,
+    date: Option<NaiveDate>,
+    category: String,
+}
+
+#[derive(Deserialize)]
+struct ParsedTransaction {
+    amount: f64,
+    kind: String,
+    category: String,
+    date: Option<NaiveDate>,
+    description: Option<String>,
+}
+
+fn category_list(kind: TransactionKind, categories: &[CategoryRow]) -> String {
+    let names: Vec<&str> = categories.iter().map(|c| c.name.as_str()).collect();
+
+    format!("{} categories: {}", kind.as_ref(), names.join(", "))
+}
+
+/// Picks the category named by the model, falling back to "other" or the first one.
+fn match_category<'a>(name: &str, categories: &'a [CategoryRow]) -> Option<&'a CategoryRow> {
+    categories
+        .iter()
+        .find(|c| c.name.eq_ignore_ascii_case(name.trim()))
+        .or_else(|| categories.iter().find(|c| c.name == "other"))
+        .or_else(|| categories.first())
+}
+
+/// Parses the JSON object out of the reply, tolerating code fences around it.
+fn parse_reply<T: DeserializeOwned>(reply: &str) -> Result<T, AppError> {
+    let start = reply.find('{');
+    let end = reply.rfind('}');
+
+    let json = match (start, end) {
+        (Some(start), Some(end)) if start < end => &reply[start..=end],
+        _ => {
+            return Err(AppError::InternalError(format!(
+                "No transaction in reply: {}",
+                reply
+            )))
+        }
+    };
+
+    Ok(serde_json::from_str(json)?)
+}
+
+/// Relative dates are meant in the chat timezone, not the one of the server.
+async fn today(db: &Database, chat_id: ChatId) -> Result<NaiveDate, AppError> {
+    let timezone = jobs::timezone_of(&db.jobs(), chat_id.0).await?;
+
+    Ok(Utc::now().with_timezone(&timezone).date_naive())
+}
+
+async fn parse(
+    text: &str,
+    chat_id: ChatId,
+    provider: &Provider,
+    db: &Database,
+) -> Result<TransactionProposal, AppError> {
+    let income = db.categories().list(TransactionKind::Income).await;
+    let spending = db.categories().list(TransactionKind::Spending).await;
+    let today = today(db, chat_id).await?;
+
+    let context = format!(
+        "Today is {}.\n{}\n{}",
+        today.format("%Y-%m-%d"),
+        category_list(TransactionKind::Income, &income),
+        category_list(TransactionKind::Spending, &spending)
+    );
+
+    let model = &CONFIG.open_ai.model;
+    let request = CompletionRequest {
+        model: model.clone(),
+        messages: vec![
+            ChatCompletionRequestSystemMessageArgs::default()
+                .content(format!("{}\n\n{}", PARSE_PROMPT, context))
+                .build()?
+                .into(),
+            ChatCompletionRequestUserMessageArgs::default()
+                .content(text)
+                .build()?
+                .into(),
+        ],
+        parameters: ModelParameters {
+            temperature: Some(0.0),
+            ..ModelParameters::default()
+        },
+        tools: Vec::new(),
+        schema: None,
+    };
+
+    let messages = request.messages.clone();
+    let started = Instant::now();
+    let completion = provider.complete(request).await?;
+
+    usage::record(
+        &db.usage(),
+        chat_id,
+        model,
+        completion
+            .usage
+            .unwrap_or_else(|| usage::estimate(model, &messages, &completion.content)),
+        started,
+    )
+    .await;
+
+    let parsed: ParsedTransaction = parse_reply(&completion.content)?;
+
+    let kind = TransactionKind::from_str(parsed.kind.trim())
+        .map_err(|_| AppError::InternalError(format!("Unknown kind: {}", parsed.kind)))?;
+
+    let categories = match kind {
+        TransactionKind::Income => &income,
+        TransactionKind::Spending => &spending,
+    };
+
+    let category = match_category(&parsed.category, categories)
+        .ok_or_else(|| AppError::InternalError(format!("No {} categories", kind.as_ref())))?;
+
+    let amount = (parsed.amount.abs() * 100.0).round() as i64;
+
+    if amount == 0 {
+        return Err(AppError::InternalError("Amount is zero".into()));
+    }
+
+    Ok(TransactionProposal {
+        amount,
+        kind,
+        category_id: category.id,
+        category_name: category.name.clone(),
+        date: parsed.date.unwrap_or(today),
+        description: parsed.description.unwrap_or_default().trim().to_string(),
+        receipt_file_id: None,
+    })
+}
+
+/// An empty `enum` is not a valid JSON schema, without categories any name is accepted.
+fn receipt_schema(categories: &[CategoryRow]) -> ResponseSchema {
+    let names: Vec<&str> = categories.iter().map(|c| c.name.as_str()).collect();
+    let category = if names.is_empty() {
+        json!({ "type": "string" })
+    } else {
+        json!({ "type": "string", "enum": names })
+    };
+
+    ResponseSchema {
+        name: "receipt",
+        schema: json!({
+            "type": "object",
+            "properties": {
+                "total": { "type": "number" },
+                "merchant": { "type": "string" },
+                "date": { "type": ["string", "null"] },
+                "category": category
+            },
+            "required": ["total", "merchant", "date", "category"],
+            "additionalProperties": false
+        }),
+    }
+}
+
+async fn parse_receipt(
+    image: String,
+    file_id: String,
+    chat_id: ChatId,
+    provider: &Provider,
+    db: &Database,
+) -> Result<TransactionProposal, AppError> {
+    let model = CONFIG
+        .open_ai
+        .vision_model()
+        .ok_or_else(|| AppError::InternalError("No vision model configured".into()))?;
+    let categories = db.categories().list(TransactionKind::Spending).await;
+    let today = today(db, chat_id).await?;
+
+    let context = format!(
+        "Today is {}.\n{}",
+        today.format("%Y-%m-%d"),
+        category_list(TransactionKind::Spending, &categories)
+    );
+
+    let photo: ChatCompletionRequestUserMessageContentPart =
+        ChatCompletionRequestMessageContentPartImageArgs::default()
+            .image_url(ImageUrlArgs::default().url(image).build()?)
+            .build()?
+            .into();
+
+    let request = CompletionRequest {
+        model: model.to_string(),
+        messages: vec![
+            ChatCompletionRequestSystemMessageArgs::default()
+                .content(format!("{}\n\n{}", RECEIPT_PROMPT, context))
+                .build()?
+                .into(),
+            ChatCompletionRequestUserMessageArgs::default()
+                .content(vec![photo])
+                .build()?
+                .into(),
+        ],
+        parameters: ModelParameters {
+            temperature: Some(0.0),
+            ..ModelParameters::default()
+        },
+        tools: Vec::new(),
+        schema: Some(receipt_schema(&categories)),
+    };
+
+    let started = Instant::now();
+    let completion = provider.complete(request).await?;
+
+    usage::record(
+        &db.usage(),
+        chat_id,
+        model,
+        completion
+            .usage
+            .unwrap_or_else(|| usage::estimate(model, &Vec::new(), &completion.content)),
+        started,
+    )
+    .await;
+
+    let parsed: ParsedReceipt = parse_reply(&completion.content)?;
+
+    let category = match_category(&parsed.category, &categories)
+        .ok_or_else(|| AppError::InternalError("No spending categories".into()))?;
+
+    let amount = (parsed.total.abs() * 100.0).round() as i64;
+
+    if amount == 0 {
+        return Err(AppError::InternalError("Receipt total is zero".into()));
+    }
+
+    Ok(TransactionProposal {
+        amount,
+        kind: TransactionKind::Spending,
+        category_id: category.id,
+        category_name: category.name.clone(),
+        date: parsed.date.unwrap_or(today),
+        description: parsed.merchant.trim().to_string(),
+        receipt_file_id: Some(file_id),
+    })
+}
+
+fn describe(proposal: &TransactionProposal) -> String {
+    format!(
+        "📝 Proposed transaction\n\n{}: {}\nCategory: {}\nDate: {}\nDescription: {}",
+        proposal.kind,
+        format_transaction_amount(proposal.kind.apply_sign(proposal.amount), "+"),
+        proposal.category_name,
+        format_transaction_date(proposal.date),
+        if proposal.description.is_empty() {
+            "-"
+        } else {
+            &proposal.description
+        }
+    )
+}
+
+pub async fn start(bot: Bot, chat_id: ChatId, dialogue: BotDialogue) -> HandleResult {
+    bot.send_message(
+        chat_id,
+        "Describe the transaction, e.g. spent 23.40 on groceries yesterday at Lidl",
+    )
+    .await?;
+
+    dialogue
+        .update(DialogueState::WaitingForTransactionText {
+            receipt_file_id: None,
+        })
+        .await?;
+
+    Ok(())
+}
+
+/// Parses free text into a transaction and asks for confirmation before saving it,
+/// `receipt_file_id` is the photo of a receipt proposal being corrected.
+pub async fn propose(
+    text: String,
+    receipt_file_id: Option<String>,
+    bot: Bot,
+    chat_id: ChatId,
+    dialogue: BotDialogue,
+    provider: Provider,
+    db: &Database,
+) -> HandleResult {
+    info!("Propose transaction, user: {}, text: {}", chat_id, text);
+
+    if !usage::check_quota(&bot, &db.usage(), chat_id).await? {
+        return Ok(());
+    }
+
+    let proposal = match parse(&text, chat_id, &provider, db).await {
+        Ok(proposal) => TransactionProposal {
+            receipt_file_id,
+            ..proposal
+        },
+        Err(e) => {
+            warn!("Failed to parse transaction: {:?}", e);
+
+            bot.send_message(
+                chat_id,
+                "⚠️ Could not understand the transaction, please describe it again.",
+            )
+            .await?;
+
+            dialogue
+                .update(DialogueState::WaitingForTransactionText { receipt_file_id })
+                .await?;
+
+            return Ok(());
+        }
+    };
+
+    offer(bot, chat_id, dialogue, proposal).await
+}
+
+/// Reads a receipt photo sent in the budgeting menu into a spending proposal.
+pub async fn receipt(
+    provider: Provider,
+    db: Arc<Database>,
+    bot: Bot,
+    dialogue: BotDialogue,
+    msg: Message,
+) -> HandleResult {
+    let chat_id = msg.chat.id;
+
+    let Some(photo) = msg.photo().and_then(|sizes| sizes.last()) else {
+        return Ok(());
+    };
+
+    info!("Propose transaction from receipt, user: {}", chat_id);
+
+    if !usage::check_quota(&bot, &db.usage(), chat_id).await? {
+        return Ok(());
+    }
+
+    bot.send_message(chat_id, "🧾 Reading the receipt...")
+        .await?;
+
+    let image = download_photo(&bot, photo).await?;
+    let file_id = photo.file.id.to_string();
+
+    let proposal = match parse_receipt(image, file_id, chat_id, &provider, &db).await {
+        Ok(proposal) => proposal,
+        Err(e) => {
+            warn!("Failed to read receipt: {:?}", e);
+
+            bot.send_message(
+                chat_id,
+                "⚠️ Could not read the receipt, try a sharper photo or describe the transaction.",
+            )
+            .await?;
+
+            return Ok(());
+        }
+    };
+
+    offer(bot, chat_id, dialogue, proposal).await
+}
+
+async fn offer(
+    bot: Bot,
+    chat_id: ChatId,
+    dialogue: BotDialogue,
+    proposal: TransactionProposal,
+) -> HandleResult {
+    bot.send_message(chat_id, describe(&proposal))
+        .reply_markup(create_transaction_proposal_keyboard())
+        .await?;
+
+    dialogue
+        .update(DialogueState::WaitingForTransactionConfirmation { proposal })
+        .await?;
+
+    Ok(())
+}
+
+pub async fn confirm(
+    bot: Bot,
+    chat_id: ChatId,
+    dialogue: BotDialogue,
+    db: &Database,
+) -> HandleResult {
+    let DialogueState::WaitingForTransactionConfirmation { proposal } =
+        dialogue.get_or_default().await?
+    else {
+        bot.send_message(chat_id, "No pending transaction.").await?;
+
+        return Ok(());
+    };
+
+    let today = today(db, chat_id).await?;
+    let date = if proposal.date == today {
+        chrono::Utc::now().timestamp()
+    } else {
+        proposal
+            .date
+            .and_hms_opt(12, 0, 0)
+            .unwrap_or_default()
+            .and_utc()
+            .timestamp()
+    };
+
+    let signed_amount = proposal.kind.apply_sign(proposal.amount);
+
+    db.transactions()
+        .add_at(
+            signed_amount,
+            Some(proposal.description.clone()),
+            chat_id.0,
+            proposal.category_id,
+            date,
+            proposal.receipt_file_id.as_deref(),
+        )
+        .await;
+
+    bot.send_message(
+        chat_id,
+        format!(
+            "{} {} {} transaction added",
+            proposal.kind,
+            format_transaction_amount(signed_amount, "+"),
+            proposal.description
+        ),
+    )
+    .await?;
+
+    dialogue.update(DialogueState::InBudgetingMenu).await?;
+
+    Ok(())
+}
+
+pub async fn edit(bot: Bot, chat_id: ChatId, dialogue: BotDialogue) -> HandleResult {
+    let receipt_file_id = match dialogue.get_or_default().await? {
+        DialogueState::WaitingForTransactionConfirmation { proposal } => proposal.receipt_file_id,
+        _ => None,
+    };
+
+    bot.send_message(
+        chat_id,
+        "Describe the transaction again with the corrections:",
+    )
+    .await?;
+
+    dialogue
+        .update(DialogueState::WaitingForTransactionText { receipt_file_id })
+        .await?;
+
+    Ok(())
+}
+
+pub async fn cancel(bot: Bot, chat_id: ChatId, dialogue: BotDialogue) -> HandleResult {
+    bot.send_message(chat_id, "❌ Transaction discarded.")
+        .await?;
+
+    dialogue.update(DialogueState::InBudgetingMenu).await?;
+
+    Ok(())
+}
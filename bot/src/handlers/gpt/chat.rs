@@ -1,82 +1,788 @@
 use crate::{
     config::CONFIG,
-    keyboard::gpt::create_gpt_menu_keyboard,
-    types::common::{BotDialogue, ChatHistoryState, DialogueState, HandleResult},
-    utils::markdown::escape_markdown_v2,
-};
-use async_openai::{
-    config::OpenAIConfig,
-    types::{ChatCompletionRequestUserMessageArgs, CreateChatCompletionRequestArgs},
-    Client,
+    handlers::gpt::{
+        context, conversations,
+        documents::{self, Retrieval},
+        history, jobs, memories,
+        reply::ReplyWriter,
+        tools, usage,
+    },
+    keyboard::gpt::{create_gpt_menu_keyboard, create_regenerate_keyboard, create_stop_keyboard},
+    providers::{CompletionChunk, CompletionRequest, Provider},
+    types::{
+        chat::{ActiveReplies, ChatRole, ChatTurn, Conversation},
+        common::{AppError, BotDialogue, ChatMessages, DialogueState, HandleResult},
+        databases::Database,
+        models::ConversationRow,
+    },
+    utils::{files::download_photo, markdown::escape_markdown_v2, tokens::count_tokens},
 };
 use futures::StreamExt;
+use std::{
+    sync::{
+        atomic::{AtomicBool, Ordering},
+        Arc,
+    },
+    time::Instant,
+};
 use teloxide::sugar::request::RequestReplyExt;
-use teloxide::{prelude::*, types::ParseMode};
-use tracing::info;
+use teloxide::{
+    prelude::*,
+    types::{MessageId, ParseMode},
+};
+use tracing::{error, info, warn};
+
+/// Tool rounds allowed per message before the answer is forced without tools.
+const MAX_TOOL_ROUNDS: usize = 5;
+
+/// Context tokens reserved for an image, the cost of a high detail 1024px image.
+const IMAGE_TOKENS: usize = 765;
+
+const CHAT_COMMAND: &str = "/chat";
 
 pub async fn message(
     content: String,
     bot: Bot,
-    client: Client<OpenAIConfig>,
-    state: ChatHistoryState,
+    provider: Provider,
+    db: &Database,
+    replies: ActiveReplies,
     msg: Message,
 ) -> HandleResult {
     info!("Complete chat, user: {}, content: {}", msg.chat.id, content);
 
-    let config = &CONFIG;
-    let hists;
-    {
-        let mut guard = state.lock().unwrap();
-        let messages = guard.entry(msg.chat.id).or_default();
-
-        messages.push(
-            ChatCompletionRequestUserMessageArgs::default()
-                .content(content)
-                .build()?
-                .into(),
+    if !usage::check_quota(&bot, &db.usage(), msg.chat.id).await? {
+        return Ok(());
+    }
+
+    if rate_limited(&bot, &replies, msg.chat.id, Some(msg.id)).await? {
+        return Ok(());
+    }
+
+    let active = conversations::for_reply(
+        &bot,
+        msg.chat.id,
+        msg.reply_to_message(),
+        &db.conversations(),
+        &db.chat_messages(),
+    )
+    .await?;
+
+    enqueue(
+        PendingTurn::Text(content),
+        bot,
+        provider,
+        db,
+        replies,
+        msg.chat.id,
+        active,
+        "💭",
+        Some(msg.id),
+    )
+    .await
+}
+
+/// Answers a scheduled prompt in the given conversation, delivered as a new message.
+/// The per-minute rate limit is not applied, jobs firing together must not be dropped.
+pub async fn scheduled(
+    prompt: &str,
+    bot: Bot,
+    provider: Provider,
+    db: &Database,
+    replies: ActiveReplies,
+    chat_id: ChatId,
+    conversation: ConversationRow,
+) -> HandleResult {
+    info!(
+        "Complete scheduled chat, user: {}, conversation: {}, content: {}",
+        chat_id, conversation.id, prompt
+    );
+
+    if !usage::check_quota(&bot, &db.usage(), chat_id).await? {
+        return Ok(());
+    }
+
+    enqueue(
+        PendingTurn::Text(prompt.to_string()),
+        bot,
+        provider,
+        db,
+        replies,
+        chat_id,
+        conversation,
+        "⏰ 💭",
+        None,
+    )
+    .await
+}
+
+/// Strips a leading `/chat` (or `/chat@bot`) command from a caption.
+fn strip_chat_command(caption: &str) -> Option<&str> {
+    let rest = caption.strip_prefix(CHAT_COMMAND)?;
+
+    match rest.chars().next() {
+        None => Some(rest),
+        Some('@') => Some(
+            rest.split_once(char::is_whitespace)
+                .map_or("", |(_, rest)| rest),
+        ),
+        Some(c) if c.is_whitespace() => Some(rest),
+        Some(_) => None,
+    }
+}
+
+/// Sends a photo with its optional caption to the AI, in chat mode, when asked for a
+/// request, with a `/chat` caption or as a reply to a `/chat` message.
+pub async fn photo(
+    provider: Provider,
+    db: Arc<Database>,
+    replies: ActiveReplies,
+    bot: Bot,
+    dialogue: BotDialogue,
+    msg: Message,
+) -> HandleResult {
+    let Some(photo) = msg.photo().and_then(|sizes| sizes.last()) else {
+        return Ok(());
+    };
+
+    let state = dialogue.get_or_default().await?;
+    let caption = msg.caption().unwrap_or_default().trim();
+    let replied_to_chat = msg
+        .reply_to_message()
+        .and_then(|replied| replied.text())
+        .is_some_and(|text| strip_chat_command(text).is_some());
+
+    let caption = match (strip_chat_command(caption), &state) {
+        (Some(rest), _) => rest.trim(),
+        (None, DialogueState::InChatMode | DialogueState::WaitingForChatRequest) => caption,
+        (None, _) if replied_to_chat => caption,
+        _ => return Ok(()),
+    };
+
+    info!(
+        "Complete chat with photo, user: {}, caption: {}",
+        msg.chat.id, caption
+    );
+
+    if state == DialogueState::WaitingForChatRequest {
+        dialogue.update(DialogueState::Start).await?;
+    }
+
+    if !usage::check_quota(&bot, &db.usage(), msg.chat.id).await? {
+        return Ok(());
+    }
+
+    if rate_limited(&bot, &replies, msg.chat.id, Some(msg.id)).await? {
+        return Ok(());
+    }
+
+    let active = conversations::for_reply(
+        &bot,
+        msg.chat.id,
+        msg.reply_to_message(),
+        &db.conversations(),
+        &db.chat_messages(),
+    )
+    .await?;
+    let model = CONFIG.open_ai.resolve_model(active.model.as_deref());
+
+    if !CONFIG.open_ai.supports_vision(&model) {
+        bot.send_message(
+            msg.chat.id,
+            format!(
+                "⚠️ {} does not accept images, select a vision model first.",
+                model
+            ),
+        )
+        .reply_to(msg.id)
+        .await?;
+
+        return Ok(());
+    }
+
+    let image = download_photo(&bot, photo).await?;
+
+    enqueue(
+        PendingTurn::Image {
+            caption: caption.to_string(),
+            image,
+        },
+        bot,
+        provider,
+        &db,
+        replies,
+        msg.chat.id,
+        active,
+        "💭",
+        Some(msg.id),
+    )
+    .await
+}
+
+/// Drops the latest answer of the conversation the message belongs to and generates it
+/// again. Older answers are refused, regenerating would replace a newer unrelated answer.
+pub async fn regenerate(
+    bot: Bot,
+    provider: Provider,
+    db: &Database,
+    replies: ActiveReplies,
+    chat_id: ChatId,
+    message_id: MessageId,
+) -> HandleResult {
+    let messages_db = db.chat_messages();
+    let mut active = None;
+
+    if let Some(link) = messages_db.find_linked(chat_id.0, message_id.0).await? {
+        if !messages_db
+            .has_user_turns_after(link.conversation_id, link.chat_message_id)
+            .await?
+        {
+            active = db
+                .conversations()
+                .find_by_id(chat_id.0, link.conversation_id)
+                .await?;
+        }
+    }
+
+    bot.edit_message_reply_markup(chat_id, message_id).await?;
+
+    let Some(active) = active else {
+        bot.send_message(
+            chat_id,
+            "⚠️ Only the latest answer can be regenerated, reply to this one to branch from it.",
+        )
+        .reply_to(message_id)
+        .await?;
+
+        return Ok(());
+    };
+
+    info!(
+        "Regenerate answer, user: {}, conversation: {}",
+        chat_id, active.id
+    );
+
+    if !usage::check_quota(&bot, &db.usage(), chat_id).await? {
+        return Ok(());
+    }
+
+    if rate_limited(&bot, &replies, chat_id, None).await? {
+        return Ok(());
+    }
+
+    enqueue(
+        PendingTurn::Regenerate,
+        bot,
+        provider,
+        db,
+        replies,
+        chat_id,
+        active,
+        "💭",
+        None,
+    )
+    .await
+}
+
+pub async fn stop(
+    bot: Bot,
+    replies: ActiveReplies,
+    chat_id: ChatId,
+    message_id: MessageId,
+) -> HandleResult {
+    if !replies.stop(chat_id.0, message_id.0) {
+        bot.edit_message_reply_markup(chat_id, message_id)
+            .reply_markup(create_regenerate_keyboard(message_id))
+            .await?;
+    }
+
+    Ok(())
+}
+
+/// Change to the history applied once a queued request gets its turn.
+enum PendingTurn {
+    Text(String),
+    Image { caption: String, image: String },
+    Regenerate,
+}
+
+/// Counts a request against the chat's per-minute rate limit, telling the user and
+/// returning true when it is reached.
+pub async fn rate_limited(
+    bot: &Bot,
+    replies: &ActiveReplies,
+    chat_id: ChatId,
+    reply_to: Option<MessageId>,
+) -> Result<bool, AppError> {
+    let limit = CONFIG.usage.requests_per_minute;
+
+    let Some(wait) = limit.and_then(|limit| replies.throttle(chat_id.0, limit)) else {
+        return Ok(false);
+    };
+
+    let mut request = bot.send_message(
+        chat_id,
+        format!(
+            "⏳ Too many AI requests, try again in {}s.",
+            wait.as_secs().max(1)
+        ),
+    );
+
+    if let Some(reply_to) = reply_to {
+        request = request.reply_to(reply_to);
+    }
+
+    request.await?;
+
+    Ok(true)
+}
+
+/// Queues the request behind earlier ones of the conversation, so turns and answers keep
+/// their order. User requests check `rate_limited` first, scheduled prompts are exempt.
+#[allow(clippy::too_many_arguments)]
+async fn enqueue(
+    pending: PendingTurn,
+    bot: Bot,
+    provider: Provider,
+    db: &Database,
+    replies: ActiveReplies,
+    chat_id: ChatId,
+    conversation: ConversationRow,
+    placeholder: &str,
+    reply_to: Option<MessageId>,
+) -> HandleResult {
+    let ahead = replies.queued(conversation.id);
+    let text = match ahead {
+        0 => placeholder.to_string(),
+        ahead => format!("⏳ Queued, {} ahead...", ahead),
+    };
+    let mut request = bot.send_message(chat_id, text);
+
+    if let Some(reply_to) = reply_to {
+        request = request.reply_to(reply_to);
+    }
+
+    let placeholder_id = request.await?.id;
+    let placeholder = placeholder.to_string();
+    let db = db.clone();
+    let queue = replies.clone();
+
+    if ahead > 0 {
+        info!(
+            "Queued request, user: {}, conversation: {}, ahead: {}",
+            chat_id, conversation.id, ahead
         );
+    }
+
+    queue.enqueue(conversation.id, async move {
+        if ahead > 0 {
+            bot.edit_message_text(chat_id, placeholder_id, placeholder)
+                .await
+                .ok();
+        }
+
+        let result = answer(
+            pending,
+            bot.clone(),
+            provider,
+            &db,
+            replies,
+            chat_id,
+            conversation,
+            placeholder_id,
+        )
+        .await;
+
+        if let Err(e) = result {
+            error!("Failed to answer, user: {}, error: {:?}", chat_id, e);
+
+            bot.send_message(chat_id, "⚠️ Failed to answer, try again.")
+                .reply_to(placeholder_id)
+                .await
+                .ok();
+        }
+    });
+
+    Ok(())
+}
 
-        hists = messages.clone();
+#[allow(clippy::too_many_arguments)]
+async fn answer(
+    pending: PendingTurn,
+    bot: Bot,
+    provider: Provider,
+    db: &Database,
+    replies: ActiveReplies,
+    chat_id: ChatId,
+    conversation: ConversationRow,
+    placeholder: MessageId,
+) -> HandleResult {
+    let messages_db = db.chat_messages();
+    let model = CONFIG.open_ai.resolve_model(conversation.model.as_deref());
+
+    match pending {
+        PendingTurn::Text(content) => {
+            messages_db
+                .add(
+                    chat_id.0,
+                    conversation.id,
+                    ChatRole::User,
+                    &content,
+                    count_tokens(&model, &content),
+                )
+                .await?;
+        }
+        PendingTurn::Image { caption, image } => {
+            messages_db
+                .add_image(
+                    chat_id.0,
+                    conversation.id,
+                    &caption,
+                    &image,
+                    count_tokens(&model, &caption) + IMAGE_TOKENS,
+                )
+                .await?;
+        }
+        PendingTurn::Regenerate => {
+            messages_db.delete_last_answer(conversation.id).await?;
+        }
+    }
+
+    respond(
+        bot,
+        provider,
+        db,
+        replies,
+        chat_id,
+        conversation,
+        placeholder,
+    )
+    .await
+}
+
+/// What every model answering a request starts from: the history with the pending user
+/// turn and the chat's memories, and the documents retrieved for the latest question.
+pub struct RequestContext {
+    pub conversation: Conversation,
+    /// Model the token counts of `conversation` are for.
+    pub model: String,
+    pub retrieval: Option<Retrieval>,
+}
+
+/// Loads the request context once, `pending` is a user turn not stored in the history.
+pub async fn request_context(
+    provider: &Provider,
+    db: &Database,
+    chat_id: ChatId,
+    active: &ConversationRow,
+    model: &str,
+    pending: Option<ChatTurn>,
+) -> Result<RequestContext, AppError> {
+    let mut conversation = history::load(&db.chat_messages(), active.id).await?;
+
+    conversation.turns.extend(pending);
+
+    memories::apply(&mut conversation, chat_id, &db.memories(), model).await?;
+
+    let question = conversation
+        .turns
+        .iter()
+        .rev()
+        .find(|turn| turn.role == ChatRole::User)
+        .map(|turn| turn.content.clone())
+        .unwrap_or_default();
+
+    let retrieval = documents::retrieve(provider, &db.documents(), &db.usage(), chat_id, &question)
+        .await
+        .unwrap_or_else(|e| {
+            warn!("Document retrieval failed: {:?}", e);
+
+            None
+        });
+
+    Ok(RequestContext {
+        conversation,
+        model: model.to_string(),
+        retrieval,
+    })
+}
+
+/// Request messages of the context as `model` gets them: images only for vision models,
+/// fitted into its context budget and with the retrieved documents before the question.
+pub async fn model_messages(
+    provider: &Provider,
+    db: &Database,
+    chat_id: ChatId,
+    active: &ConversationRow,
+    request: &RequestContext,
+    model: &str,
+) -> Result<ChatMessages, AppError> {
+    let config = &CONFIG;
+    let mut conversation = request.conversation.clone();
+
+    if model != request.model {
+        conversation = conversation.recounted(model);
+    }
+
+    if !config.open_ai.supports_vision(model) {
+        conversation = conversation.without_images();
     }
 
-    let response = bot.send_message(msg.chat.id, "💭").reply_to(msg.id).await?;
+    let conversation = context::fit(
+        provider,
+        &db.chat_messages(),
+        &db.usage(),
+        chat_id.0,
+        active.id,
+        conversation,
+        model,
+    )
+    .await?;
+
+    let mut messages = conversation.to_request_messages(config.open_ai.history_limit)?;
+
+    if let Some(retrieval) = &request.retrieval {
+        messages.insert(messages.len().saturating_sub(1), retrieval.message.clone());
+    }
+
+    Ok(messages)
+}
+
+/// Request messages of the conversation as `model` gets them, with memories and document
+/// context. `pending` is a user turn not stored in the history.
+pub async fn request_messages(
+    provider: &Provider,
+    db: &Database,
+    chat_id: ChatId,
+    active: &ConversationRow,
+    model: &str,
+    pending: Option<ChatTurn>,
+) -> Result<(ChatMessages, Option<Retrieval>), AppError> {
+    let request = request_context(provider, db, chat_id, active, model, pending).await?;
+    let messages = model_messages(provider, db, chat_id, active, &request, model).await?;
+
+    Ok((messages, request.retrieval))
+}
+
+/// Streams the answer to the latest user turn of the conversation into `placeholder`.
+#[allow(clippy::too_many_arguments)]
+async fn respond(
+    bot: Bot,
+    provider: Provider,
+    db: &Database,
+    replies: ActiveReplies,
+    chat_id: ChatId,
+    active: ConversationRow,
+    placeholder: MessageId,
+) -> HandleResult {
+    let stop_flag = replies.register(chat_id.0, placeholder.0);
+    let result = stream_answer(
+        bot.clone(),
+        provider,
+        db,
+        chat_id,
+        active,
+        placeholder,
+        &stop_flag,
+    )
+    .await;
+
+    replies.finish(chat_id.0, placeholder.0);
+
+    result
+}
+
+async fn stream_answer(
+    bot: Bot,
+    provider: Provider,
+    db: &Database,
+    chat_id: ChatId,
+    active: ConversationRow,
+    placeholder: MessageId,
+    stop_flag: &AtomicBool,
+) -> HandleResult {
+    let config = &CONFIG;
+    let messages_db = db.chat_messages();
+    let usage_db = db.usage();
+    let model = config.open_ai.resolve_model(active.model.as_deref());
+    let parameters = config.open_ai.parameters(&model);
+
+    let (mut hists, retrieval) =
+        request_messages(&provider, db, chat_id, &active, &model, None).await?;
+
+    // Budgeting tools and memories belong to the owner, groups get plain answers.
+    let offer_tools = chat_id.is_user();
+
+    if offer_tools {
+        let timezone = jobs::timezone_of(&db.jobs(), chat_id.0).await?;
+
+        hists.insert(
+            hists.len().saturating_sub(1),
+            tools::date_context(timezone)?,
+        );
+    }
+
+    let mut writer = ReplyWriter::new(bot.clone(), chat_id, placeholder);
+    let mut reply = String::new();
+    let mut round = 0;
+
+    writer.set_markup(create_stop_keyboard(placeholder)).await?;
 
-    let msg_id = response.id;
-    let request = CreateChatCompletionRequestArgs::default()
-        .model(config.open_ai.model.clone())
-        .messages(hists)
-        .build()?;
+    loop {
+        let request = CompletionRequest {
+            model: model.clone(),
+            messages: hists.clone(),
+            parameters,
+            tools: if round < MAX_TOOL_ROUNDS && offer_tools {
+                tools::definitions()
+            } else {
+                Vec::new()
+            },
+            schema: None,
+        };
 
-    let mut stream = client.chat().create_stream(request).await?;
-    let mut chunks = Vec::new();
-    let mut count = 0;
+        let messages = request.messages.clone();
+        let started = Instant::now();
+        let mut stream = provider.stream(request).await?;
+        let mut tool_calls = Vec::new();
+        let mut round_usage = None;
 
-    while let Some(result) = stream.next().await {
-        if let Some(ref content) = result?.choices.first().unwrap().delta.content {
-            chunks.push(content.to_owned());
+        reply.clear();
 
-            if !content.trim().is_empty() {
-                count += 1;
-                if count % 20 == 0 {
-                    bot.edit_message_text(msg.chat.id, msg_id, chunks.join(""))
-                        .await?;
+        while let Some(result) = stream.next().await {
+            if stop_flag.load(Ordering::SeqCst) {
+                break;
+            }
+
+            match result? {
+                CompletionChunk::Content(content) => {
+                    reply.push_str(&content);
+                    writer.update(&reply).await?;
                 }
+                CompletionChunk::ToolCalls(calls) => tool_calls = calls,
+                CompletionChunk::Usage(reported) => round_usage = Some(reported),
+            }
+        }
+
+        let round_usage = round_usage.unwrap_or_else(|| {
+            let output = format!(
+                "{}{}",
+                reply,
+                serde_json::to_string(&tool_calls).unwrap_or_default()
+            );
+
+            usage::estimate(&model, &messages, &output)
+        });
+
+        usage::record(&usage_db, chat_id, &model, round_usage, started).await;
+
+        if tool_calls.is_empty() || stop_flag.load(Ordering::SeqCst) {
+            break;
+        }
+
+        round += 1;
+
+        let names: Vec<&str> = tool_calls.iter().map(|call| call.name.as_str()).collect();
+
+        writer.status(&format!("🛠 {}", names.join(", "))).await?;
+
+        let partial = reply.clone();
+        let serialized = serde_json::to_string(&tool_calls)
+            .map_err(|e| AppError::InternalError(format!("Invalid tool calls: {}", e)))?;
+
+        let id = messages_db
+            .add_tool_calls(
+                chat_id.0,
+                active.id,
+                &partial,
+                &serialized,
+                count_tokens(&model, &serialized),
+            )
+            .await?;
+
+        hists.push(
+            ChatTurn {
+                id,
+                role: ChatRole::Assistant,
+                content: partial,
+                tokens: 0,
+                tool_calls: tool_calls.clone(),
+                tool_call_id: None,
+                image: None,
+                created_at: chrono::Utc::now().naive_utc(),
             }
+            .to_request_message()?,
+        );
+
+        for call in &tool_calls {
+            let output = tools::execute(call, &bot, chat_id, db).await;
+
+            let id = messages_db
+                .add_tool_result(
+                    chat_id.0,
+                    active.id,
+                    &call.id,
+                    &output,
+                    count_tokens(&model, &output),
+                )
+                .await?;
+
+            hists.push(
+                ChatTurn {
+                    id,
+                    role: ChatRole::Tool,
+                    content: output,
+                    tokens: 0,
+                    tool_calls: Vec::new(),
+                    tool_call_id: Some(call.id.clone()),
+                    image: None,
+                    created_at: chrono::Utc::now().naive_utc(),
+                }
+                .to_request_message()?,
+            );
+        }
+    }
+
+    let stopped = stop_flag.load(Ordering::SeqCst);
+
+    if stopped {
+        info!(
+            "Answer stopped, user: {}, conversation: {}",
+            chat_id, active.id
+        );
+
+        // Nothing was generated, the marker is only shown and no empty answer is stored.
+        if reply.trim().is_empty() {
+            writer.clear_markup().await?;
+            writer.finish("⏹ Stopped.").await?;
+
+            return Ok(());
         }
     }
 
-    bot.edit_message_text(msg.chat.id, msg_id, chunks.join(""))
+    // Sources are only shown, the stored answer stays what the model wrote.
+    let sources = retrieval
+        .filter(|_| !stopped)
+        .map(|retrieval| format!("\n\n📎 {}", retrieval.sources.join(", ")))
+        .unwrap_or_default();
+
+    writer
+        .set_markup(create_regenerate_keyboard(placeholder))
         .await?;
+    writer.finish(&format!("{}{}", reply, sources)).await?;
 
-    let mut guard = state.lock().unwrap();
-    let messages = guard.entry(msg.chat.id).or_default();
+    let id = messages_db
+        .add(
+            chat_id.0,
+            active.id,
+            ChatRole::Assistant,
+            &reply,
+            count_tokens(&model, &reply),
+        )
+        .await?;
 
-    messages.push(
-        ChatCompletionRequestUserMessageArgs::default()
-            .content(chunks.join(""))
-            .build()?
-            .into(),
-    );
+    let rendered: Vec<i32> = writer.messages().iter().map(|message| message.0).collect();
+
+    messages_db.link(chat_id.0, &rendered, id).await?;
 
     Ok(())
 }
@@ -127,11 +833,110 @@ pub async fn exit(bot: Bot, dialogue: BotDialogue, msg: Message) -> HandleResult
 }
 
 pub async fn message_in_chat_mode(
-    client: Client<OpenAIConfig>,
-    state: ChatHistoryState,
+    provider: Provider,
+    db: Arc<Database>,
+    replies: ActiveReplies,
     bot: Bot,
     msg: Message,
     text: String,
 ) -> HandleResult {
-    message(text, bot, client, state, msg).await
+    message(text, bot, provider, &db, replies, msg).await
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::providers::mock::MockProvider;
+    use async_openai::types::{
+        ChatCompletionRequestMessage, ChatCompletionRequestSystemMessageContent,
+        ChatCompletionRequestUserMessageContent,
+    };
+
+    const CHAT_ID: ChatId = ChatId(1);
+
+    fn text(message: &ChatCompletionRequestMessage) -> String {
+        match message {
+            ChatCompletionRequestMessage::System(system) => match &system.content {
+                ChatCompletionRequestSystemMessageContent::Text(text) => text.clone(),
+                _ => String::new(),
+            },
+            ChatCompletionRequestMessage::User(user) => match &user.content {
+                ChatCompletionRequestUserMessageContent::Text(text) => text.clone(),
+                _ => String::new(),
+            },
+            _ => String::new(),
+        }
+    }
+
+    #[tokio::test]
+    async fn builds_request_messages_with_memories_and_documents() {
+        let db = Database::in_memory().await;
+        let provider: Provider = Arc::new(MockProvider::new(vec![], vec![]));
+        let active = db.conversations().active(CHAT_ID.0).await.unwrap();
+        let messages_db = db.chat_messages();
+        let model = "mock";
+
+        messages_db
+            .set_system(CHAT_ID.0, active.id, "Be brief.", 3)
+            .await
+            .unwrap();
+        messages_db
+            .add(CHAT_ID.0, active.id, ChatRole::User, "Hi", 1)
+            .await
+            .unwrap();
+        messages_db
+            .add(CHAT_ID.0, active.id, ChatRole::Assistant, "Hello!", 2)
+            .await
+            .unwrap();
+        db.memories()
+            .add(CHAT_ID.0, "Has a cat", false)
+            .await
+            .unwrap();
+
+        let chunks = vec!["The lease allows pets up to 10 kg.".to_string()];
+        let embeddings = provider.embed(model, chunks.clone()).await.unwrap().vectors;
+
+        db.documents()
+            .add(CHAT_ID.0, "lease.txt", &chunks, &embeddings)
+            .await
+            .unwrap();
+
+        let pending = ChatTurn {
+            id: 0,
+            role: ChatRole::User,
+            content: "Does the lease allow pets?".into(),
+            tokens: 0,
+            tool_calls: Vec::new(),
+            tool_call_id: None,
+            image: None,
+            created_at: chrono::Utc::now().naive_utc(),
+        };
+
+        let (messages, retrieval) =
+            request_messages(&provider, &db, CHAT_ID, &active, model, Some(pending))
+                .await
+                .unwrap();
+
+        let texts: Vec<String> = messages.iter().map(text).collect();
+
+        assert_eq!(texts.len(), 5);
+        assert!(texts[0].starts_with("Known facts about the user:\n- Has a cat"));
+        assert!(texts[0].ends_with("Be brief."));
+        assert_eq!(texts[1], "Hi");
+        assert!(texts[3].contains("The lease allows pets up to 10 kg."));
+        assert_eq!(texts[4], "Does the lease allow pets?");
+        assert_eq!(
+            retrieval.map(|retrieval| retrieval.sources),
+            Some(vec!["[1] lease.txt #1".to_string()])
+        );
+
+        // Nothing from the flow is stored, the pending turn is up to the caller.
+        assert_eq!(messages_db.list(active.id).await.unwrap().len(), 3);
+
+        let usage = db.usage().totals_since(CHAT_ID.0, 0).await.unwrap();
+
+        assert_eq!(usage.len(), 1);
+        assert_eq!(usage[0].model, CONFIG.documents.embedding_model);
+        assert!(usage[0].prompt_tokens > 0);
+    }
 }
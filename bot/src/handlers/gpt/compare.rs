@@ -0,0 +1,441 @@
+use futures::{future::join_all, StreamExt};
+use std::time::Instant;
+use teloxide::{prelude::*, sugar::request::RequestReplyExt, types::MessageId};
+use tracing::{error, info};
+
+use crate::{
+    config::CONFIG,
+    handlers::gpt::{chat, conversations, reply::ReplyWriter, usage},
+    keyboard::gpt::create_comparison_keyboard,
+    providers::{CompletionChunk, CompletionRequest, Provider},
+    types::{
+        chat::{ActiveReplies, ChatRole, ChatTurn},
+        common::{AppError, ChatMessages, HandleResult},
+        databases::Database,
+        models::{ComparisonAnswerRow, ConversationRow},
+    },
+    utils::tokens::count_tokens,
+};
+
+/// Sends the prompt to every comparison model, each answer streams into its own message.
+/// Nothing is stored in the conversation until one of the answers is picked.
+pub async fn compare(
+    prompt: String,
+    bot: Bot,
+    provider: Provider,
+    db: &Database,
+    replies: ActiveReplies,
+    msg: Message,
+) -> HandleResult {
+    let chat_id = msg.chat.id;
+    let prompt = prompt.trim().to_string();
+
+    if prompt.is_empty() {
+        bot.send_message(chat_id, "Usage: /compare Explain monads in one paragraph")
+            .await?;
+
+        return Ok(());
+    }
+
+    let models = CONFIG.open_ai.compare_models();
+
+    if models.len() < 2 {
+        bot.send_message(
+            chat_id,
+            "⚠️ Comparison needs at least two models in open_ai.compare_models.",
+        )
+        .await?;
+
+        return Ok(());
+    }
+
+    if !usage::check_quota(&bot, &db.usage(), chat_id).await? {
+        return Ok(());
+    }
+
+    if chat::rate_limited(&bot, &replies, chat_id, Some(msg.id)).await? {
+        return Ok(());
+    }
+
+    let active = conversations::for_reply(
+        &bot,
+        chat_id,
+        msg.reply_to_message(),
+        &db.conversations(),
+        &db.chat_messages(),
+    )
+    .await?;
+
+    info!(
+        "Compare, user: {}, conversation: {}, models: {}",
+        chat_id,
+        active.id,
+        models.join(", ")
+    );
+
+    let ahead = replies.queued(active.id);
+
+    if ahead > 0 {
+        bot.send_message(chat_id, format!("⏳ Comparison queued, {} ahead...", ahead))
+            .reply_to(msg.id)
+            .await?;
+    }
+
+    let db = db.clone();
+    let reply_to = msg.id;
+
+    replies.enqueue(active.id, async move {
+        let result = run(
+            bot.clone(),
+            provider,
+            &db,
+            chat_id,
+            active,
+            prompt,
+            models,
+            reply_to,
+        )
+        .await;
+
+        if let Err(e) = result {
+            error!("Failed to compare, user: {}, error: {:?}", chat_id, e);
+
+            bot.send_message(chat_id, "⚠️ Failed to compare, try again.")
+                .reply_to(reply_to)
+                .await
+                .ok();
+        }
+    });
+
+    Ok(())
+}
+
+/// The history, memories and retrieved documents are loaded once for all models, each one
+/// only gets them fitted into its own context budget.
+#[allow(clippy::too_many_arguments)]
+async fn run(
+    bot: Bot,
+    provider: Provider,
+    db: &Database,
+    chat_id: ChatId,
+    active: ConversationRow,
+    prompt: String,
+    models: Vec<String>,
+    reply_to: MessageId,
+) -> HandleResult {
+    let model = CONFIG.open_ai.resolve_model(active.model.as_deref());
+    let pending = ChatTurn {
+        id: 0,
+        role: ChatRole::User,
+        content: prompt.clone(),
+        tokens: count_tokens(&model, &prompt),
+        tool_calls: Vec::new(),
+        tool_call_id: None,
+        image: None,
+        created_at: chrono::Utc::now().naive_utc(),
+    };
+
+    let request =
+        chat::request_context(&provider, db, chat_id, &active, &model, Some(pending)).await?;
+
+    let last_message_id = request
+        .conversation
+        .turns
+        .iter()
+        .map(|turn| turn.id)
+        .max()
+        .unwrap_or_default();
+
+    let comparison_id = db
+        .comparisons()
+        .create(chat_id.0, active.id, &prompt, last_message_id)
+        .await?;
+
+    let sources = request
+        .retrieval
+        .as_ref()
+        .map(|retrieval| retrieval.sources.join(", "));
+
+    let mut answers = Vec::new();
+
+    for model in models {
+        let placeholder = bot
+            .send_message(chat_id, format!("🆚 {}\n💭", model))
+            .reply_to(reply_to)
+            .await?
+            .id;
+
+        let messages =
+            chat::model_messages(&provider, db, chat_id, &active, &request, &model).await?;
+
+        answers.push(answer(
+            bot.clone(),
+            &provider,
+            db,
+            chat_id,
+            comparison_id,
+            model,
+            messages,
+            sources.clone(),
+            placeholder,
+        ));
+    }
+
+    join_all(answers).await;
+
+    Ok(())
+}
+
+/// Streams one model's answer, a failure is shown in its message without stopping the others.
+#[allow(clippy::too_many_arguments)]
+async fn answer(
+    bot: Bot,
+    provider: &Provider,
+    db: &Database,
+    chat_id: ChatId,
+    comparison_id: i64,
+    model: String,
+    messages: ChatMessages,
+    sources: Option<String>,
+    placeholder: MessageId,
+) {
+    let result = stream_answer(
+        bot.clone(),
+        provider,
+        db,
+        chat_id,
+        comparison_id,
+        &model,
+        messages,
+        sources,
+        placeholder,
+    )
+    .await;
+
+    if let Err(e) = result {
+        error!(
+            "Comparison answer failed, user: {}, model: {}, error: {:?}",
+            chat_id, model, e
+        );
+
+        bot.edit_message_text(
+            chat_id,
+            placeholder,
+            format!("🆚 {}\n⚠️ Failed to answer.", model),
+        )
+        .await
+        .ok();
+    }
+}
+
+#[allow(clippy::too_many_arguments)]
+async fn stream_answer(
+    bot: Bot,
+    provider: &Provider,
+    db: &Database,
+    chat_id: ChatId,
+    comparison_id: i64,
+    model: &str,
+    messages: ChatMessages,
+    sources: Option<String>,
+    placeholder: MessageId,
+) -> HandleResult {
+    let header = format!("🆚 **{}**\n\n", model);
+    let mut writer = ReplyWriter::new(bot, chat_id, placeholder);
+    let mut reply = String::new();
+    let mut reported = None;
+    let started = Instant::now();
+
+    let mut stream = provider
+        .stream(CompletionRequest {
+            model: model.to_string(),
+            messages: messages.clone(),
+            parameters: CONFIG.open_ai.parameters(model),
+            tools: Vec::new(),
+            schema: None,
+        })
+        .await?;
+
+    while let Some(result) = stream.next().await {
+        match result? {
+            CompletionChunk::Content(content) => {
+                reply.push_str(&content);
+                writer.update(&format!("{}{}", header, reply)).await?;
+            }
+            CompletionChunk::ToolCalls(_) => {}
+            CompletionChunk::Usage(usage) => reported = Some(usage),
+        }
+    }
+
+    let latency = started.elapsed();
+    let tokens = reported.unwrap_or_else(|| usage::estimate(model, &messages, &reply));
+    let completion_tokens = tokens.completion_tokens;
+
+    usage::record(&db.usage(), chat_id, model, tokens, started).await;
+
+    // Sources are only shown, the stored answer is what a pick adds to the history.
+    let sources = sources
+        .map(|sources| format!("\n\n📎 {}", sources))
+        .unwrap_or_default();
+
+    writer
+        .finish(&format!(
+            "{}{}{}\n\n⏱ {:.1}s · {} tokens",
+            header,
+            reply,
+            sources,
+            latency.as_secs_f64(),
+            completion_tokens
+        ))
+        .await?;
+
+    let rendered: Vec<i32> = writer.messages().iter().map(|message| message.0).collect();
+    let message_ids = serde_json::to_string(&rendered)
+        .map_err(|e| AppError::InternalError(format!("Invalid message ids: {}", e)))?;
+
+    let id = db
+        .comparisons()
+        .add_answer(comparison_id, model, &reply, &message_ids)
+        .await?;
+
+    writer.set_markup(create_comparison_keyboard(id)).await?;
+
+    Ok(())
+}
+
+/// Continues the conversation with the picked answer. The pick waits behind the queued
+/// requests of the conversation, so its turns land after theirs.
+pub async fn pick(
+    answer_id: i64,
+    bot: Bot,
+    chat_id: ChatId,
+    db: &Database,
+    replies: ActiveReplies,
+) -> HandleResult {
+    let Some(answer) = db.comparisons().answer(chat_id.0, answer_id).await? else {
+        bot.send_message(chat_id, "⚠️ Comparison not found.")
+            .await?;
+
+        return Ok(());
+    };
+
+    let db = db.clone();
+
+    replies.enqueue(answer.conversation_id, async move {
+        if let Err(e) = continue_with(answer, bot.clone(), chat_id, &db).await {
+            error!("Failed to pick answer, user: {}, error: {:?}", chat_id, e);
+
+            bot.send_message(chat_id, "⚠️ Failed to pick the answer, try again.")
+                .await
+                .ok();
+        }
+    });
+
+    Ok(())
+}
+
+/// The prompt and the answer become the latest turns of the conversation and the buttons
+/// of all answers of the comparison are removed. Refused once the conversation moved on,
+/// the answer would no longer follow its history.
+async fn continue_with(
+    answer: ComparisonAnswerRow,
+    bot: Bot,
+    chat_id: ChatId,
+    db: &Database,
+) -> HandleResult {
+    let comparisons_db = db.comparisons();
+    let messages_db = db.chat_messages();
+
+    let Some(conversation) = db
+        .conversations()
+        .find_by_id(chat_id.0, answer.conversation_id)
+        .await?
+    else {
+        bot.send_message(chat_id, "⚠️ Conversation not found.")
+            .await?;
+
+        return Ok(());
+    };
+
+    if messages_db
+        .has_user_turns_after(conversation.id, answer.last_message_id)
+        .await?
+    {
+        bot.send_message(
+            chat_id,
+            format!(
+                "⚠️ \"{}\" moved on since the comparison, its answers can no longer be picked.",
+                conversation.name
+            ),
+        )
+        .await?;
+
+        return Ok(());
+    }
+
+    if !comparisons_db.pick(answer.comparison_id, answer.id).await? {
+        bot.send_message(chat_id, "An answer of this comparison is already picked.")
+            .await?;
+
+        return Ok(());
+    }
+
+    info!(
+        "Comparison picked, user: {}, conversation: {}, model: {}",
+        chat_id, conversation.id, answer.model
+    );
+
+    let model = CONFIG.open_ai.resolve_model(conversation.model.as_deref());
+
+    messages_db
+        .add(
+            chat_id.0,
+            conversation.id,
+            ChatRole::User,
+            &answer.prompt,
+            count_tokens(&model, &answer.prompt),
+        )
+        .await?;
+
+    let id = messages_db
+        .add(
+            chat_id.0,
+            conversation.id,
+            ChatRole::Assistant,
+            &answer.content,
+            count_tokens(&model, &answer.content),
+        )
+        .await?;
+
+    let rendered = message_ids(&answer.message_ids);
+
+    messages_db.link(chat_id.0, &rendered, id).await?;
+
+    for sibling in comparisons_db.answers(answer.comparison_id).await? {
+        if let Some(&first) = message_ids(&sibling.message_ids).first() {
+            bot.edit_message_reply_markup(chat_id, MessageId(first))
+                .await
+                .ok();
+        }
+    }
+
+    let mut request = bot.send_message(
+        chat_id,
+        format!(
+            "✅ {} answer continues \"{}\".",
+            answer.model, conversation.name
+        ),
+    );
+
+    if let Some(&first) = rendered.first() {
+        request = request.reply_to(MessageId(first));
+    }
+
+    request.await?;
+
+    Ok(())
+}
+
+fn message_ids(serialized: &str) -> Vec<i32> {
+    serde_json::from_str(serialized).unwrap_or_default()
+}
@@ -0,0 +1,326 @@
+use async_openai::types::{
+    ChatCompletionRequestSystemMessageArgs, ChatCompletionRequestUserMessageArgs,
+};
+use std::time::Instant;
+use teloxide::types::ChatId;
+use tracing::{info, warn};
+
+use crate::{
+    config::{ContextStrategy, ModelParameters, CONFIG},
+    handlers::gpt::usage,
+    providers::{CompletionRequest, Provider},
+    types::{
+        chat::{ChatRole, ChatTurn, Conversation},
+        common::AppError,
+        databases::{ChatMessagesDb, UsageDb},
+    },
+    utils::tokens::count_tokens,
+};
+
+const SUMMARY_PROMPT: &str = "Summarize the conversation below in a few short paragraphs. \
+Keep names, numbers, decisions and open questions. Reply with the summary only.";
+
+fn transcript(summary: Option<&ChatTurn>, turns: &[ChatTurn]) -> String {
+    let mut lines: Vec<String> = Vec::new();
+
+    if let Some(summary) = summary {
+        lines.push(format!("Earlier summary: {}", summary.content));
+    }
+
+    for turn in turns {
+        let speaker = match turn.role {
+            ChatRole::User => "User",
+            ChatRole::Assistant => "Assistant",
+            ChatRole::System => "System",
+            ChatRole::Tool => "Tool",
+        };
+
+        if turn.tool_calls.is_empty() {
+            lines.push(format!("{}: {}", speaker, turn.content));
+        }
+
+        for call in &turn.tool_calls {
+            lines.push(format!(
+                "{}: called {}({})",
+                speaker, call.name, call.arguments
+            ));
+        }
+    }
+
+    lines.join("\n\n")
+}
+
+#[allow(clippy::too_many_arguments)]
+async fn summarize(
+    provider: &Provider,
+    messages_db: &ChatMessagesDb,
+    usage_db: &UsageDb,
+    chat_id: i64,
+    conversation_id: i64,
+    conversation: Conversation,
+    model: &str,
+    budget: usize,
+) -> Result<Conversation, AppError> {
+    let (collapsed, recent) = conversation.split_for_summary(budget / 2);
+
+    if collapsed.is_empty() {
+        return Ok(conversation.truncated(budget));
+    }
+
+    let request = CompletionRequest {
+        model: model.to_string(),
+        messages: vec![
+            ChatCompletionRequestSystemMessageArgs::default()
+                .content(SUMMARY_PROMPT)
+                .build()?
+                .into(),
+            ChatCompletionRequestUserMessageArgs::default()
+                .content(transcript(conversation.summary.as_ref(), &collapsed))
+                .build()?
+                .into(),
+        ],
+        parameters: ModelParameters::default(),
+        tools: Vec::new(),
+        schema: None,
+    };
+
+    let messages = request.messages.clone();
+    let started = Instant::now();
+    let completion = provider.complete(request).await?;
+    let summary = completion.content;
+
+    usage::record(
+        usage_db,
+        ChatId(chat_id),
+        model,
+        completion
+            .usage
+            .unwrap_or_else(|| usage::estimate(model, &messages, &summary)),
+        started,
+    )
+    .await;
+
+    if summary.trim().is_empty() {
+        return Err(AppError::InternalError("Empty summary response".into()));
+    }
+
+    let tokens = count_tokens(model, &summary);
+    let collapsed_ids: Vec<i64> = collapsed.iter().map(|turn| turn.id).collect();
+
+    let id = messages_db
+        .summarize(chat_id, conversation_id, &collapsed_ids, &summary, tokens)
+        .await?;
+
+    info!(
+        "Collapsed {} turns into summary, conversation: {}",
+        collapsed_ids.len(),
+        conversation_id
+    );
+
+    let summarized = Conversation {
+        system: conversation.system,
+        summary: Some(ChatTurn {
+            id,
+            role: ChatRole::System,
+            content: summary,
+            tokens,
+            tool_calls: Vec::new(),
+            tool_call_id: None,
+            image: None,
+            created_at: chrono::Utc::now().naive_utc(),
+        }),
+        turns: recent,
+    };
+
+    Ok(summarized.truncated(budget))
+}
+
+/// Makes the conversation fit the context budget of the model, the system prompt is always kept.
+pub async fn fit(
+    provider: &Provider,
+    messages_db: &ChatMessagesDb,
+    usage_db: &UsageDb,
+    chat_id: i64,
+    conversation_id: i64,
+    conversation: Conversation,
+    model: &str,
+) -> Result<Conversation, AppError> {
+    let budget = CONFIG.open_ai.context_budget(model);
+
+    if conversation.total_tokens() <= budget {
+        return Ok(conversation);
+    }
+
+    match CONFIG.open_ai.context_strategy {
+        ContextStrategy::Truncate => Ok(conversation.truncated(budget)),
+        ContextStrategy::Summarize => {
+            let fallback = conversation.clone();
+
+            match summarize(
+                provider,
+                messages_db,
+                usage_db,
+                chat_id,
+                conversation_id,
+                conversation,
+                model,
+                budget,
+            )
+            .await
+            {
+                Ok(summarized) => Ok(summarized),
+                Err(e) => {
+                    warn!("Summarization failed, truncating instead: {:?}", e);
+
+                    Ok(fallback.truncated(budget))
+                }
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        handlers::gpt::history, providers::mock::MockProvider, types::databases::Database,
+    };
+    use std::sync::Arc;
+
+    const CHAT_ID: i64 = 1;
+    const MODEL: &str = "mock";
+
+    /// Conversation with a system prompt and `turns` alternating user and assistant turns.
+    async fn seeded(db: &Database, turns: &[usize]) -> (i64, Conversation) {
+        let conversation_id = db.conversations().create(CHAT_ID, "test").await.unwrap();
+        let messages_db = db.chat_messages();
+
+        messages_db
+            .set_system(CHAT_ID, conversation_id, "Be brief.", 10)
+            .await
+            .unwrap();
+
+        for (index, &tokens) in turns.iter().enumerate() {
+            let role = if index % 2 == 0 {
+                ChatRole::User
+            } else {
+                ChatRole::Assistant
+            };
+
+            messages_db
+                .add(
+                    CHAT_ID,
+                    conversation_id,
+                    role,
+                    &format!("turn {}", index),
+                    tokens,
+                )
+                .await
+                .unwrap();
+        }
+
+        let conversation = history::load(&messages_db, conversation_id).await.unwrap();
+
+        (conversation_id, conversation)
+    }
+
+    #[tokio::test]
+    async fn keeps_conversation_within_budget_untouched() {
+        let db = Database::in_memory().await;
+        let provider: Provider = Arc::new(MockProvider::new(vec![], vec![]));
+        let budget = CONFIG.open_ai.context_budget(MODEL);
+        let (conversation_id, conversation) = seeded(&db, &[budget - 10]).await;
+
+        let fitted = fit(
+            &provider,
+            &db.chat_messages(),
+            &db.usage(),
+            CHAT_ID,
+            conversation_id,
+            conversation,
+            MODEL,
+        )
+        .await
+        .unwrap();
+
+        assert_eq!(fitted.total_tokens(), budget);
+        assert_eq!(fitted.turns.len(), 1);
+        assert!(fitted.summary.is_none());
+    }
+
+    #[tokio::test]
+    async fn fits_conversation_over_budget_keeping_system_prompt() {
+        let db = Database::in_memory().await;
+        let provider: Provider = Arc::new(MockProvider::new(vec![], vec![]));
+        let budget = CONFIG.open_ai.context_budget(MODEL);
+        let (conversation_id, conversation) = seeded(&db, &[1, budget - 10]).await;
+
+        let fitted = fit(
+            &provider,
+            &db.chat_messages(),
+            &db.usage(),
+            CHAT_ID,
+            conversation_id,
+            conversation,
+            MODEL,
+        )
+        .await
+        .unwrap();
+
+        assert!(fitted.total_tokens() <= budget);
+        assert_eq!(
+            fitted.system.map(|system| system.content).as_deref(),
+            Some("Be brief.")
+        );
+        assert_eq!(
+            fitted.turns.last().map(|turn| turn.content.as_str()),
+            Some("turn 1")
+        );
+    }
+
+    #[tokio::test]
+    async fn summary_replaces_collapsed_turns() {
+        let db = Database::in_memory().await;
+        let provider: Provider = Arc::new(MockProvider::new(vec!["Short summary.".into()], vec![]));
+        let messages_db = db.chat_messages();
+        let (conversation_id, conversation) = seeded(&db, &[400, 400, 400, 400]).await;
+
+        let summarized = summarize(
+            &provider,
+            &messages_db,
+            &db.usage(),
+            CHAT_ID,
+            conversation_id,
+            conversation,
+            MODEL,
+            1000,
+        )
+        .await
+        .unwrap();
+
+        assert_eq!(
+            summarized.summary.map(|summary| summary.content).as_deref(),
+            Some("Short summary.")
+        );
+        assert_eq!(
+            summarized
+                .turns
+                .iter()
+                .map(|turn| turn.content.as_str())
+                .collect::<Vec<_>>(),
+            vec!["turn 3"]
+        );
+
+        let stored = history::load(&messages_db, conversation_id).await.unwrap();
+
+        assert_eq!(
+            stored.system.map(|system| system.content).as_deref(),
+            Some("Be brief.")
+        );
+        assert_eq!(
+            stored.summary.map(|summary| summary.content).as_deref(),
+            Some("Short summary.")
+        );
+        assert_eq!(stored.turns.len(), 1);
+    }
+}
@@ -0,0 +1,253 @@
+use chrono::Local;
+use teloxide::prelude::*;
+use tracing::info;
+
+use crate::{
+    config::CONFIG,
+    keyboard::gpt::create_conversations_keyboard,
+    types::{
+        common::{AppError, HandleResult},
+        databases::{ChatMessagesDb, ConversationsDb},
+        models::ConversationRow,
+    },
+};
+
+fn conversation_name(name: &str) -> String {
+    let trimmed = name.trim();
+
+    if trimmed.is_empty() {
+        format!("Chat {}", Local::now().format("%Y-%m-%d %H:%M"))
+    } else {
+        trimmed.to_string()
+    }
+}
+
+pub async fn create(
+    name: String,
+    bot: Bot,
+    chat_id: ChatId,
+    conversations_db: &ConversationsDb,
+) -> HandleResult {
+    let name = conversation_name(&name);
+
+    info!("Create conversation, user: {}, name: {}", chat_id, name);
+
+    match conversations_db.create(chat_id.0, &name).await {
+        Ok(id) => {
+            conversations_db.set_active(chat_id.0, id).await?;
+
+            bot.send_message(chat_id, format!("🆕 Started conversation '{}'.", name))
+                .await?;
+        }
+        Err(AppError::InternalError(message)) => {
+            bot.send_message(chat_id, format!("⚠️ {}", message)).await?;
+        }
+        Err(e) => return Err(e),
+    }
+
+    Ok(())
+}
+
+fn branch_name(parent: &ConversationRow, conversations: &[ConversationRow]) -> String {
+    let branches = conversations
+        .iter()
+        .filter(|conversation| conversation.parent_id == Some(parent.id))
+        .count();
+    let name = format!("{} ⑂{}", parent.name, branches + 1);
+
+    if conversations
+        .iter()
+        .any(|conversation| conversation.name == name)
+    {
+        format!("{} {}", name, Local::now().format("%H:%M:%S"))
+    } else {
+        name
+    }
+}
+
+/// Returns the conversation a message continues. Replying to an older answer forks
+/// a branch at that answer and makes the branch active.
+pub async fn for_reply(
+    bot: &Bot,
+    chat_id: ChatId,
+    replied: Option<&Message>,
+    conversations_db: &ConversationsDb,
+    messages_db: &ChatMessagesDb,
+) -> Result<ConversationRow, AppError> {
+    let active = conversations_db.active(chat_id.0).await?;
+
+    let Some(replied) = replied else {
+        return Ok(active);
+    };
+
+    let Some(link) = messages_db.find_linked(chat_id.0, replied.id.0).await? else {
+        return Ok(active);
+    };
+
+    let conversations = conversations_db.list(chat_id.0).await?;
+
+    let Some(parent) = conversations
+        .iter()
+        .find(|conversation| conversation.id == link.conversation_id)
+    else {
+        return Ok(active);
+    };
+
+    if !messages_db
+        .has_user_turns_after(parent.id, link.chat_message_id)
+        .await?
+    {
+        if parent.id != active.id {
+            conversations_db.set_active(chat_id.0, parent.id).await?;
+        }
+
+        return Ok(parent.clone());
+    }
+
+    let name = branch_name(parent, &conversations);
+    let id = conversations_db
+        .fork(chat_id.0, parent, &name, link.chat_message_id)
+        .await?;
+
+    conversations_db.set_active(chat_id.0, id).await?;
+
+    info!(
+        "Branch conversation, user: {}, parent: {}, branch: {}",
+        chat_id, parent.id, id
+    );
+
+    bot.send_message(
+        chat_id,
+        format!("⑂ Continuing in branch '{}' of '{}'.", name, parent.name),
+    )
+    .await?;
+
+    conversations_db.active(chat_id.0).await
+}
+
+pub async fn list(bot: Bot, chat_id: ChatId, conversations_db: &ConversationsDb) -> HandleResult {
+    let active = conversations_db.active(chat_id.0).await?;
+    let conversations = conversations_db.list(chat_id.0).await?;
+
+    let content = conversations
+        .iter()
+        .map(|conversation| {
+            let marker = if conversation.id == active.id {
+                "▶️"
+            } else {
+                "▫️"
+            };
+
+            format!(
+                "{} {} - {} ({} messages, {})",
+                marker,
+                conversation.id,
+                conversation.name,
+                conversation.messages_count,
+                CONFIG.open_ai.resolve_model(conversation.model.as_deref())
+            )
+        })
+        .collect::<Vec<String>>()
+        .join("\n");
+
+    bot.send_message(chat_id, format!("🗂 Conversations\n\n{}", content))
+        .reply_markup(create_conversations_keyboard(&conversations, active.id))
+        .await?;
+
+    Ok(())
+}
+
+pub async fn switch(
+    key: String,
+    bot: Bot,
+    chat_id: ChatId,
+    conversations_db: &ConversationsDb,
+) -> HandleResult {
+    let Some(conversation) = conversations_db.find(chat_id.0, &key).await? else {
+        bot.send_message(
+            chat_id,
+            format!("⚠️ Conversation '{}' not found.", key.trim()),
+        )
+        .await?;
+
+        return Ok(());
+    };
+
+    conversations_db
+        .set_active(chat_id.0, conversation.id)
+        .await?;
+
+    bot.send_message(
+        chat_id,
+        format!("🔀 Switched to conversation '{}'.", conversation.name),
+    )
+    .await?;
+
+    Ok(())
+}
+
+pub async fn rename(
+    name: String,
+    bot: Bot,
+    chat_id: ChatId,
+    conversations_db: &ConversationsDb,
+) -> HandleResult {
+    let name = name.trim();
+
+    if name.is_empty() {
+        bot.send_message(chat_id, "❌ Conversation name cannot be empty")
+            .await?;
+
+        return Ok(());
+    }
+
+    let active = conversations_db.active(chat_id.0).await?;
+
+    match conversations_db.rename(active.id, name).await {
+        Ok(()) => {
+            bot.send_message(
+                chat_id,
+                format!("✏️ Renamed '{}' to '{}'.", active.name, name),
+            )
+            .await?;
+        }
+        Err(AppError::InternalError(message)) => {
+            bot.send_message(chat_id, format!("⚠️ {}", message)).await?;
+        }
+        Err(e) => return Err(e),
+    }
+
+    Ok(())
+}
+
+pub async fn delete(
+    key: String,
+    bot: Bot,
+    chat_id: ChatId,
+    conversations_db: &ConversationsDb,
+) -> HandleResult {
+    let Some(conversation) = conversations_db.find(chat_id.0, &key).await? else {
+        bot.send_message(
+            chat_id,
+            format!("⚠️ Conversation '{}' not found.", key.trim()),
+        )
+        .await?;
+
+        return Ok(());
+    };
+
+    conversations_db.delete(conversation.id).await?;
+
+    let active = conversations_db.active(chat_id.0).await?;
+
+    bot.send_message(
+        chat_id,
+        format!(
+            "🗑 Deleted conversation '{}'. Active conversation: '{}'.",
+            conversation.name, active.name
+        ),
+    )
+    .await?;
+
+    Ok(())
+}
@@ -0,0 +1,311 @@
+use async_openai::types::{ChatCompletionRequestMessage, ChatCompletionRequestSystemMessageArgs};
+use std::{sync::Arc, time::Instant};
+use teloxide::{prelude::*, sugar::request::RequestReplyExt, types::Document};
+use tracing::info;
+
+use crate::{
+    config::CONFIG,
+    handlers::gpt::usage,
+    keyboard::gpt::create_documents_keyboard,
+    providers::Provider,
+    types::{
+        common::{AppError, HandleResult},
+        databases::{Database, DocumentsDb, UsageDb},
+    },
+    utils::{
+        embeddings::{chunk_text, cosine_similarity, decode_vector},
+        files::download,
+    },
+};
+
+/// Largest file the Bot API lets bots download.
+const MAX_DOCUMENT_SIZE: u32 = 20 * 1024 * 1024;
+
+/// Chunks embedded per provider request.
+const EMBEDDING_BATCH: usize = 64;
+
+const RETRIEVAL_PROMPT: &str = "Excerpts of the user's documents that may help with the \
+next question are listed below. Base the answer on them when they are relevant and cite \
+them as [n]. Ignore them when they do not relate to the question.";
+
+enum DocumentFormat {
+    Text,
+    Pdf,
+}
+
+fn document_format(document: &Document) -> Option<DocumentFormat> {
+    let name = document
+        .file_name
+        .as_deref()
+        .unwrap_or_default()
+        .to_lowercase();
+    let mime = document
+        .mime_type
+        .as_ref()
+        .map(|mime| mime.essence_str().to_string())
+        .unwrap_or_default();
+
+    if mime == "application/pdf" || name.ends_with(".pdf") {
+        Some(DocumentFormat::Pdf)
+    } else if mime.starts_with("text/")
+        || [".txt", ".md", ".markdown"]
+            .iter()
+            .any(|extension| name.ends_with(extension))
+    {
+        Some(DocumentFormat::Text)
+    } else {
+        None
+    }
+}
+
+async fn extract_text(data: Vec<u8>, format: DocumentFormat) -> Result<String, AppError> {
+    match format {
+        DocumentFormat::Text => Ok(String::from_utf8_lossy(&data).into_owned()),
+        DocumentFormat::Pdf => tokio::task::spawn_blocking(move || {
+            pdf_extract::extract_text_from_mem(&data)
+                .map_err(|e| AppError::InternalError(format!("Unreadable PDF: {}", e)))
+        })
+        .await
+        .map_err(|e| AppError::InternalError(format!("PDF extraction failed: {}", e)))?,
+    }
+}
+
+/// Chunks and embeds an uploaded text, Markdown or PDF document for retrieval in chat.
+/// Embeds `inputs` with the configured model and records the tokens as its usage.
+async fn embed(
+    provider: &Provider,
+    usage_db: &UsageDb,
+    chat_id: ChatId,
+    inputs: Vec<String>,
+) -> Result<Vec<Vec<f32>>, AppError> {
+    let model = &CONFIG.documents.embedding_model;
+    let started = Instant::now();
+    let embeddings = provider.embed(model, inputs.clone()).await?;
+
+    usage::record(
+        usage_db,
+        chat_id,
+        model,
+        embeddings
+            .usage
+            .unwrap_or_else(|| usage::estimate_embedding(model, &inputs)),
+        started,
+    )
+    .await;
+
+    Ok(embeddings.vectors)
+}
+
+pub async fn upload(provider: Provider, db: Arc<Database>, bot: Bot, msg: Message) -> HandleResult {
+    let Some(document) = msg.document() else {
+        return Ok(());
+    };
+
+    let name = document
+        .file_name
+        .clone()
+        .unwrap_or_else(|| "document".to_string());
+
+    let Some(format) = document_format(document) else {
+        bot.send_message(
+            msg.chat.id,
+            "⚠️ Only text, Markdown and PDF documents can be indexed.",
+        )
+        .reply_to(msg.id)
+        .await?;
+
+        return Ok(());
+    };
+
+    if document.file.size > MAX_DOCUMENT_SIZE {
+        bot.send_message(msg.chat.id, "⚠️ The document is larger than 20 MB.")
+            .reply_to(msg.id)
+            .await?;
+
+        return Ok(());
+    }
+
+    if !usage::check_quota(&bot, &db.usage(), msg.chat.id).await? {
+        return Ok(());
+    }
+
+    info!("Index document, user: {}, name: {}", msg.chat.id, name);
+
+    let data = download(&bot, &document.file.id).await?;
+    let text = extract_text(data, format).await?;
+    let config = &CONFIG.documents;
+    let chunks = chunk_text(&text, config.chunk_size, config.chunk_overlap);
+
+    if chunks.is_empty() {
+        bot.send_message(msg.chat.id, "⚠️ No text found in the document.")
+            .reply_to(msg.id)
+            .await?;
+
+        return Ok(());
+    }
+
+    let mut embeddings = Vec::with_capacity(chunks.len());
+
+    for batch in chunks.chunks(EMBEDDING_BATCH) {
+        embeddings.extend(embed(&provider, &db.usage(), msg.chat.id, batch.to_vec()).await?);
+    }
+
+    if embeddings.len() != chunks.len() {
+        return Err(AppError::InternalError(format!(
+            "Got {} embeddings for {} chunks",
+            embeddings.len(),
+            chunks.len()
+        )));
+    }
+
+    db.documents()
+        .add(msg.chat.id.0, &name, &chunks, &embeddings)
+        .await?;
+
+    bot.send_message(
+        msg.chat.id,
+        format!(
+            "📄 Indexed '{}' in {} chunks, ask about it in the chat.",
+            name,
+            chunks.len()
+        ),
+    )
+    .reply_to(msg.id)
+    .await?;
+
+    Ok(())
+}
+
+pub async fn list(bot: Bot, chat_id: ChatId, documents_db: &DocumentsDb) -> HandleResult {
+    let documents = documents_db.list(chat_id.0).await?;
+
+    if documents.is_empty() {
+        bot.send_message(chat_id, "📄 No documents indexed, send a file to add one.")
+            .await?;
+
+        return Ok(());
+    }
+
+    let content = documents
+        .iter()
+        .map(|document| {
+            format!(
+                "{} - {} ({} chunks, {})",
+                document.id,
+                document.name,
+                document.chunks,
+                document.created_at.format("%Y-%m-%d")
+            )
+        })
+        .collect::<Vec<String>>()
+        .join("\n");
+
+    bot.send_message(chat_id, format!("📄 Documents\n\n{}", content))
+        .reply_markup(create_documents_keyboard(&documents))
+        .await?;
+
+    Ok(())
+}
+
+/// Deletes an indexed document by its id or name.
+pub async fn delete(
+    key: String,
+    bot: Bot,
+    chat_id: ChatId,
+    documents_db: &DocumentsDb,
+) -> HandleResult {
+    let key = key.trim();
+    let id = key.parse::<i64>().unwrap_or(-1);
+
+    let Some(document) = documents_db
+        .list(chat_id.0)
+        .await?
+        .into_iter()
+        .find(|document| document.id == id || document.name == key)
+    else {
+        bot.send_message(chat_id, format!("⚠️ Document '{}' not found.", key))
+            .await?;
+
+        return Ok(());
+    };
+
+    documents_db.delete(chat_id.0, document.id).await?;
+
+    bot.send_message(chat_id, format!("🗑 Deleted document '{}'.", document.name))
+        .await?;
+
+    Ok(())
+}
+
+/// Excerpts picked for a question, `message` goes into the request and `sources`
+/// are listed under the answer.
+pub struct Retrieval {
+    pub message: ChatCompletionRequestMessage,
+    pub sources: Vec<String>,
+}
+
+/// Finds the chunks closest to `question`, `None` when the chat has no relevant documents.
+pub async fn retrieve(
+    provider: &Provider,
+    documents_db: &DocumentsDb,
+    usage_db: &UsageDb,
+    chat_id: ChatId,
+    question: &str,
+) -> Result<Option<Retrieval>, AppError> {
+    let chunks = documents_db.chunks(chat_id.0).await?;
+
+    if chunks.is_empty() || question.trim().is_empty() {
+        return Ok(None);
+    }
+
+    let config = &CONFIG.documents;
+    let query = embed(provider, usage_db, chat_id, vec![question.to_string()])
+        .await?
+        .pop()
+        .unwrap_or_default();
+
+    let mut scored: Vec<(f32, _)> = chunks
+        .iter()
+        .map(|chunk| {
+            (
+                cosine_similarity(&query, &decode_vector(&chunk.embedding)),
+                chunk,
+            )
+        })
+        .filter(|(score, _)| *score >= config.min_score)
+        .collect();
+
+    scored.sort_by(|a, b| b.0.total_cmp(&a.0));
+    scored.truncate(config.top_k);
+
+    if scored.is_empty() {
+        return Ok(None);
+    }
+
+    let sources: Vec<String> = scored
+        .iter()
+        .enumerate()
+        .map(|(index, (_, chunk))| {
+            format!(
+                "[{}] {} #{}",
+                index + 1,
+                chunk.document_name,
+                chunk.position + 1
+            )
+        })
+        .collect();
+
+    let excerpts = scored
+        .iter()
+        .zip(&sources)
+        .map(|((_, chunk), source)| format!("{}\n{}", source, chunk.content))
+        .collect::<Vec<String>>()
+        .join("\n\n");
+
+    let message = ChatCompletionRequestSystemMessageArgs::default()
+        .content(format!("{}\n\n{}", RETRIEVAL_PROMPT, excerpts))
+        .build()?
+        .into();
+
+    Ok(Some(Retrieval { message, sources }))
+}
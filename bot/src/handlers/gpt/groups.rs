@@ -0,0 +1,174 @@
+use std::sync::Arc;
+use teloxide::{
+    prelude::*,
+    types::{Me, MessageEntity, MessageEntityKind, MessageEntityRef, MessageId},
+};
+use tracing::{debug, info};
+
+use crate::{
+    config::CONFIG,
+    handlers::gpt::chat,
+    providers::Provider,
+    types::{chat::ActiveReplies, common::HandleResult, databases::Database},
+};
+
+/// Whether the bot answers in the group chat.
+pub fn is_allowed(chat_id: ChatId) -> bool {
+    CONFIG.groups.allowed.contains(&chat_id.0)
+}
+
+fn speaker(msg: &Message) -> String {
+    msg.from
+        .as_ref()
+        .map(|user| user.full_name())
+        .or_else(|| {
+            msg.sender_chat
+                .as_ref()
+                .and_then(|chat| chat.title())
+                .map(str::to_string)
+        })
+        .unwrap_or_else(|| "Someone".to_string())
+}
+
+/// Removes the first `@username` mention of the bot, `None` when it is not mentioned.
+/// Mentions are taken from the message entities, so `@username_bot` or an e-mail
+/// address containing the username do not count.
+fn strip_mention(text: &str, entities: &[MessageEntity], username: &str) -> Option<String> {
+    let mention = format!("@{}", username);
+    let entity = MessageEntityRef::parse(text, entities)
+        .into_iter()
+        .find(|entity| {
+            *entity.kind() == MessageEntityKind::Mention
+                && entity.text().eq_ignore_ascii_case(&mention)
+        })?;
+
+    Some(format!(
+        "{}{}",
+        &text[..entity.start()],
+        &text[entity.end()..]
+    ))
+}
+
+/// Answers a group message mentioning the bot or replying to it. The speaker's name is
+/// prefixed to the turn, the history is kept per group.
+pub async fn message(
+    provider: Provider,
+    replies: ActiveReplies,
+    bot: Bot,
+    me: Me,
+    db: Arc<Database>,
+    msg: Message,
+) -> HandleResult {
+    let Some(text) = msg.text() else {
+        return Ok(());
+    };
+
+    let replied_to_bot = msg
+        .reply_to_message()
+        .and_then(|replied| replied.from.as_ref())
+        .is_some_and(|from| from.id == me.id);
+
+    let entities = msg.entities().unwrap_or_default();
+
+    let content = match strip_mention(text, entities, me.username()) {
+        Some(content) => content,
+        None if replied_to_bot => text.to_string(),
+        None => return Ok(()),
+    };
+
+    let content = content.trim();
+
+    if content.is_empty() {
+        return Ok(());
+    }
+
+    info!(
+        "Group chat, group: {}, speaker: {}",
+        msg.chat.id,
+        speaker(&msg)
+    );
+
+    let content = format!("{}: {}", speaker(&msg), content);
+
+    chat::message(content, bot, provider, &db, replies, msg).await
+}
+
+/// Stop and regenerate buttons under answers in a group, usable by any member.
+pub async fn callback(
+    provider: Provider,
+    replies: ActiveReplies,
+    bot: Bot,
+    db: Arc<Database>,
+    q: CallbackQuery,
+) -> HandleResult {
+    bot.answer_callback_query(q.id.clone()).await?;
+
+    let Some(chat_id) = q.message.as_ref().map(|message| message.chat().id) else {
+        return Ok(());
+    };
+
+    if !is_allowed(chat_id) {
+        return Ok(());
+    }
+
+    let parts: Vec<&str> = q.data.as_deref().unwrap_or_default().split(':').collect();
+
+    match parts.as_slice() {
+        ["reply", "stop", id] => {
+            let message_id = MessageId(id.parse().unwrap_or_default());
+
+            chat::stop(bot, replies, chat_id, message_id).await?;
+        }
+        ["reply", "regenerate", id] => {
+            let message_id = MessageId(id.parse().unwrap_or_default());
+
+            chat::regenerate(bot, provider, &db, replies, chat_id, message_id).await?;
+        }
+        _ => debug!("Ignored group callback: {:?}", q.data),
+    }
+
+    Ok(())
+}
+
+/// Group messages outside the allow-list, or not meant for the AI, are left unanswered.
+pub async fn ignore(msg: Message) -> HandleResult {
+    debug!("Ignored message from chat {}", msg.chat.id);
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn mention(text: &str, offset: usize, length: usize) -> Option<String> {
+        let entities = [MessageEntity::new(
+            MessageEntityKind::Mention,
+            offset,
+            length,
+        )];
+
+        strip_mention(text, &entities, "xyzzy_bot")
+    }
+
+    #[test]
+    fn strips_the_mention_entity() {
+        assert_eq!(
+            mention("@Xyzzy_Bot what is 2+2?", 0, 10).as_deref(),
+            Some(" what is 2+2?")
+        );
+        assert_eq!(
+            mention("Привет @xyzzy_bot!", 7, 10).as_deref(),
+            Some("Привет !")
+        );
+    }
+
+    #[test]
+    fn ignores_other_mentions_and_plain_text() {
+        assert_eq!(mention("@xyzzy_bot_helper hi", 0, 17), None);
+        assert_eq!(
+            strip_mention("mail me@xyzzy_bot.com", &[], "xyzzy_bot"),
+            None
+        );
+    }
+}
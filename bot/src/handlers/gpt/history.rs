@@ -1,71 +1,301 @@
-use async_openai::types::ChatCompletionRequestMessage;
-use async_openai::types::ChatCompletionRequestToolMessageContent;
-use teloxide::{prelude::*, sugar::request::RequestReplyExt};
+use teloxide::{
+    prelude::*,
+    sugar::request::RequestReplyExt,
+    types::{InlineKeyboardMarkup, MessageId},
+};
 
-use crate::types::common::{ChatHistoryState, HandleResult};
+use crate::{
+    keyboard::gpt::create_history_keyboard,
+    types::{
+        chat::{ChatRole, ChatTurn, Conversation},
+        common::{AppError, HandleResult},
+        databases::{ChatMessagesDb, ConversationsDb},
+        models::ConversationRow,
+    },
+    utils::markdown::{split_message, MESSAGE_LIMIT},
+};
 
-fn print_msg(msg: &ChatCompletionRequestMessage) -> String {
-    match msg {
-        ChatCompletionRequestMessage::User(f) => {
-            format!("[User]: {:?}", f.content.to_owned())
-        }
-        ChatCompletionRequestMessage::Developer(f) => {
-            format!("[Developer]: {:?}", f.content.to_owned())
-        }
-        ChatCompletionRequestMessage::Tool(f) => {
-            let content_str = match f.content {
-                ChatCompletionRequestToolMessageContent::Text(ref s) => s,
-                _ => "No content or complex tool output (Tool response).",
-            };
+pub async fn load(
+    messages_db: &ChatMessagesDb,
+    conversation_id: i64,
+) -> Result<Conversation, AppError> {
+    let rows = messages_db.list(conversation_id).await?;
 
-            format!("[Tool]: {}", content_str)
-        }
-        ChatCompletionRequestMessage::System(f) => {
-            format!("[System]: {:?}", f.content)
-        }
-        ChatCompletionRequestMessage::Function(f) => {
-            format!("[Function]: {:?}", f.content)
-        }
-        ChatCompletionRequestMessage::Assistant(f) => {
-            format!("[Assistant]: {:?}", f.content.to_owned())
-        }
+    Conversation::from_rows(rows)
+}
+
+/// Turns shown per history page.
+const TURNS_PER_PAGE: usize = 8;
+
+/// Characters of a turn shown on a page, longer turns get an expand button.
+const PREVIEW_CHARS: usize = 300;
+
+/// All turns of the conversation in history order, the system prompt and summary first.
+fn all_turns(conversation: Conversation) -> Vec<ChatTurn> {
+    conversation
+        .system
+        .into_iter()
+        .chain(conversation.summary)
+        .chain(conversation.turns)
+        .collect()
+}
+
+fn label(turn: &ChatTurn, is_summary: bool) -> &'static str {
+    match turn.role {
+        ChatRole::System if is_summary => "📝 Summary",
+        ChatRole::System => "⚙️ System",
+        ChatRole::User => "🧑 User",
+        ChatRole::Assistant => "🤖 Assistant",
+        ChatRole::Tool => "🛠 Tool result",
     }
 }
 
-pub async fn view(bot: Bot, state: ChatHistoryState, msg: Message) -> HandleResult {
-    let content = {
-        let mut guard = state.lock().unwrap();
-        let messages = guard.entry(msg.chat.id).or_default();
-
-        if messages.is_empty() {
-            "Empty chat history.".to_owned()
-        } else {
-            messages
-                .iter()
-                .map(print_msg)
-                .collect::<Vec<String>>()
-                .join("\n\n")
+/// Full text of a turn, tool calls and images included.
+fn turn_text(turn: &ChatTurn) -> String {
+    let mut parts = Vec::new();
+
+    if turn.image.is_some() {
+        parts.push("[image]".to_string());
+    }
+
+    if !turn.content.trim().is_empty() {
+        parts.push(turn.content.trim().to_string());
+    }
+
+    for call in &turn.tool_calls {
+        parts.push(format!("🛠 {}({})", call.name, call.arguments));
+    }
+
+    parts.join("\n")
+}
+
+fn preview(text: &str) -> (String, bool) {
+    if text.chars().count() <= PREVIEW_CHARS {
+        return (text.to_string(), false);
+    }
+
+    let cut: String = text.chars().take(PREVIEW_CHARS).collect();
+
+    (format!("{}…", cut.trim_end()), true)
+}
+
+/// Renders one page of the history and the ids of its truncated turns by number.
+fn render_page(
+    header: &str,
+    turns: &[ChatTurn],
+    summary_id: Option<i64>,
+    page: usize,
+) -> (String, Vec<(usize, i64)>) {
+    let pages = turns.len().div_ceil(TURNS_PER_PAGE).max(1);
+    let first = page * TURNS_PER_PAGE;
+    let mut truncated = Vec::new();
+
+    let body = turns
+        .iter()
+        .enumerate()
+        .skip(first)
+        .take(TURNS_PER_PAGE)
+        .map(|(index, turn)| {
+            let number = index + 1;
+            let (text, is_truncated) = preview(&turn_text(turn));
+
+            if is_truncated {
+                truncated.push((number, turn.id));
+            }
+
+            format!(
+                "#{} {} · {}\n{}",
+                number,
+                label(turn, Some(turn.id) == summary_id),
+                turn.created_at.format("%Y-%m-%d %H:%M"),
+                text
+            )
+        })
+        .collect::<Vec<String>>()
+        .join("\n\n");
+
+    (
+        format!("{}\nPage {}/{}\n\n{}", header, page + 1, pages, body),
+        truncated,
+    )
+}
+
+/// Lists the branches sharing a root with the active conversation, marking the active one.
+fn branches(active: &ConversationRow, conversations: &[ConversationRow]) -> String {
+    let parent_of = |id: i64| {
+        conversations
+            .iter()
+            .find(|conversation| conversation.id == id)
+            .and_then(|conversation| conversation.parent_id)
+    };
+    let root_of = |mut id: i64| {
+        while let Some(parent_id) = parent_of(id) {
+            id = parent_id;
         }
+
+        id
     };
 
-    bot.send_message(msg.chat.id, content)
+    let root = root_of(active.id);
+    let family: Vec<String> = conversations
+        .iter()
+        .filter(|conversation| root_of(conversation.id) == root)
+        .map(|conversation| {
+            if conversation.id == active.id {
+                format!("▶️ {}", conversation.name)
+            } else {
+                conversation.name.clone()
+            }
+        })
+        .collect();
+
+    if family.len() < 2 {
+        return String::new();
+    }
+
+    format!("\nBranches: {}", family.join(" · "))
+}
+
+struct HistoryPage {
+    text: String,
+    keyboard: InlineKeyboardMarkup,
+}
+
+/// Builds a page of the conversation, the latest page when `page` is out of range.
+async fn history_page(
+    conversation: &ConversationRow,
+    conversations_db: &ConversationsDb,
+    messages_db: &ChatMessagesDb,
+    page: Option<usize>,
+) -> Result<HistoryPage, AppError> {
+    let loaded = load(messages_db, conversation.id).await?;
+    let conversations = conversations_db.list(conversation.chat_id).await?;
+    let header = format!(
+        "[{}]{}",
+        conversation.name,
+        branches(conversation, &conversations)
+    );
+
+    if loaded.is_empty() {
+        return Ok(HistoryPage {
+            text: format!("{}\n\nEmpty chat history.", header),
+            keyboard: InlineKeyboardMarkup::default(),
+        });
+    }
+
+    let summary_id = loaded.summary.as_ref().map(|summary| summary.id);
+    let turns = all_turns(loaded);
+    let pages = turns.len().div_ceil(TURNS_PER_PAGE).max(1);
+    let page = page.filter(|page| *page < pages).unwrap_or(pages - 1);
+    let (text, truncated) = render_page(&header, &turns, summary_id, page);
+
+    Ok(HistoryPage {
+        text,
+        keyboard: create_history_keyboard(conversation.id, page, pages, &truncated),
+    })
+}
+
+pub async fn view(
+    bot: Bot,
+    conversations_db: &ConversationsDb,
+    messages_db: &ChatMessagesDb,
+    msg: Message,
+) -> HandleResult {
+    let active = conversations_db.active(msg.chat.id.0).await?;
+    let page = history_page(&active, conversations_db, messages_db, None).await?;
+
+    bot.send_message(msg.chat.id, page.text)
+        .reply_markup(page.keyboard)
         .reply_to(msg.id)
         .await?;
 
     Ok(())
 }
 
-pub async fn clear(bot: Bot, state: ChatHistoryState, msg: Message) -> HandleResult {
+/// Shows another page of a history message in place.
+pub async fn page(
+    conversation_id: i64,
+    page: usize,
+    bot: Bot,
+    chat_id: ChatId,
+    message_id: MessageId,
+    conversations_db: &ConversationsDb,
+    messages_db: &ChatMessagesDb,
+) -> HandleResult {
+    let Some(conversation) = conversations_db
+        .find_by_id(chat_id.0, conversation_id)
+        .await?
+    else {
+        bot.send_message(chat_id, "⚠️ Conversation not found.")
+            .await?;
+
+        return Ok(());
+    };
+
+    let page = history_page(&conversation, conversations_db, messages_db, Some(page)).await?;
+
+    bot.edit_message_text(chat_id, message_id, page.text)
+        .reply_markup(page.keyboard)
+        .await?;
+
+    Ok(())
+}
+
+/// Sends the full text of a turn truncated on a history page.
+pub async fn expand(
+    conversation_id: i64,
+    turn_id: i64,
+    bot: Bot,
+    chat_id: ChatId,
+    conversations_db: &ConversationsDb,
+    messages_db: &ChatMessagesDb,
+) -> HandleResult {
+    let turn = match conversations_db
+        .find_by_id(chat_id.0, conversation_id)
+        .await?
     {
-        let mut guard = state.lock().unwrap();
-        let messages = guard.entry(msg.chat.id).or_default();
+        Some(conversation) => all_turns(load(messages_db, conversation.id).await?)
+            .into_iter()
+            .find(|turn| turn.id == turn_id),
+        None => None,
+    };
+
+    let Some(turn) = turn else {
+        bot.send_message(
+            chat_id,
+            "⚠️ Message not found, it may have been summarized.",
+        )
+        .await?;
 
-        messages.clear();
+        return Ok(());
+    };
+
+    for part in split_message(&turn_text(&turn), MESSAGE_LIMIT) {
+        bot.send_message(chat_id, part).await?;
     }
 
-    bot.send_message(msg.chat.id, "Chat history cleared.")
-        .reply_to(msg.id)
-        .await?;
+    Ok(())
+}
+
+pub async fn clear(
+    bot: Bot,
+    conversations_db: &ConversationsDb,
+    messages_db: &ChatMessagesDb,
+    msg: Message,
+) -> HandleResult {
+    let active = conversations_db.active(msg.chat.id.0).await?;
+
+    messages_db.clear(active.id).await?;
+
+    bot.send_message(
+        msg.chat.id,
+        format!(
+            "Chat history of '{}' cleared, system prompt kept.",
+            active.name
+        ),
+    )
+    .reply_to(msg.id)
+    .await?;
 
     Ok(())
 }
@@ -0,0 +1,251 @@
+use chrono::DateTime;
+use chrono_tz::Tz;
+use std::sync::Arc;
+use teloxide::prelude::*;
+use tracing::{info, warn};
+
+use crate::{
+    config::CONFIG,
+    handlers::gpt::chat,
+    keyboard::gpt::create_jobs_keyboard,
+    providers::Provider,
+    types::{
+        chat::ActiveReplies,
+        common::{AppError, HandleResult},
+        databases::{ConversationsDb, Database, JobsDb},
+        models::{ConversationRow, JobRow},
+    },
+    utils::schedule,
+};
+
+const USAGE: &str = "Usage: /schedule <cron> | <prompt>, e.g.\n\
+    /schedule 0 8 * * Mon-Fri | Summarise yesterday's spending and give me a tip";
+
+/// Timezone of the chat's schedules, the configured default when unset or unknown.
+pub async fn timezone_of(jobs_db: &JobsDb, chat_id: i64) -> Result<Tz, AppError> {
+    let name = jobs_db
+        .timezone(chat_id)
+        .await?
+        .unwrap_or_else(|| CONFIG.scheduler.timezone.clone());
+
+    Ok(name.parse().unwrap_or_else(|_| {
+        warn!("Unknown timezone '{}', falling back to UTC", name);
+
+        Tz::UTC
+    }))
+}
+
+fn format_run(timestamp: i64, timezone: Tz) -> String {
+    DateTime::from_timestamp(timestamp, 0)
+        .map(|time| {
+            time.with_timezone(&timezone)
+                .format("%a %Y-%m-%d %H:%M")
+                .to_string()
+        })
+        .unwrap_or_default()
+}
+
+pub async fn create(text: String, bot: Bot, chat_id: ChatId, jobs_db: &JobsDb) -> HandleResult {
+    let Some((expression, prompt)) = text
+        .split_once('|')
+        .map(|(expression, prompt)| (expression.trim(), prompt.trim()))
+        .filter(|(expression, prompt)| !expression.is_empty() && !prompt.is_empty())
+    else {
+        bot.send_message(chat_id, USAGE).await?;
+
+        return Ok(());
+    };
+
+    let schedule = match schedule::parse(expression) {
+        Ok(schedule) => schedule,
+        Err(e) => {
+            bot.send_message(
+                chat_id,
+                format!("⚠️ Invalid schedule '{}': {}\n\n{}", expression, e, USAGE),
+            )
+            .await?;
+
+            return Ok(());
+        }
+    };
+
+    let timezone = timezone_of(jobs_db, chat_id.0).await?;
+    let now = chrono::Utc::now().timestamp();
+
+    let Some(next_run) = schedule::next_run(&schedule, timezone, now) else {
+        bot.send_message(chat_id, "⚠️ The schedule never runs again.")
+            .await?;
+
+        return Ok(());
+    };
+
+    let id = jobs_db.add(chat_id.0, expression, prompt, next_run).await?;
+
+    info!(
+        "Schedule job, user: {}, id: {}, schedule: {}",
+        chat_id, id, expression
+    );
+
+    bot.send_message(
+        chat_id,
+        format!(
+            "⏰ Job {} scheduled, next run {} ({}).",
+            id,
+            format_run(next_run, timezone),
+            timezone
+        ),
+    )
+    .await?;
+
+    Ok(())
+}
+
+pub async fn list(bot: Bot, chat_id: ChatId, jobs_db: &JobsDb) -> HandleResult {
+    let jobs = jobs_db.list(chat_id.0).await?;
+    let timezone = timezone_of(jobs_db, chat_id.0).await?;
+
+    if jobs.is_empty() {
+        bot.send_message(chat_id, format!("⏰ No scheduled jobs.\n\n{}", USAGE))
+            .await?;
+
+        return Ok(());
+    }
+
+    let content = jobs
+        .iter()
+        .map(|job| {
+            format!(
+                "{} - {} | {}\nNext run: {}",
+                job.id,
+                job.schedule,
+                job.prompt,
+                format_run(job.next_run, timezone)
+            )
+        })
+        .collect::<Vec<String>>()
+        .join("\n\n");
+
+    bot.send_message(
+        chat_id,
+        format!("⏰ Scheduled jobs ({})\n\n{}", timezone, content),
+    )
+    .reply_markup(create_jobs_keyboard(&jobs))
+    .await?;
+
+    Ok(())
+}
+
+pub async fn delete(key: String, bot: Bot, chat_id: ChatId, jobs_db: &JobsDb) -> HandleResult {
+    let deleted = match key.trim().parse::<i64>() {
+        Ok(id) => jobs_db.delete(chat_id.0, id).await?,
+        Err(_) => false,
+    };
+
+    let message = if deleted {
+        format!("🗑 Job {} deleted.", key.trim())
+    } else {
+        format!("⚠️ Job '{}' not found.", key.trim())
+    };
+
+    bot.send_message(chat_id, message).await?;
+
+    Ok(())
+}
+
+/// Sets the chat's timezone and moves the next runs of its jobs accordingly.
+pub async fn set_timezone(
+    name: String,
+    bot: Bot,
+    chat_id: ChatId,
+    jobs_db: &JobsDb,
+) -> HandleResult {
+    let name = name.trim();
+
+    if name.is_empty() {
+        let timezone = timezone_of(jobs_db, chat_id.0).await?;
+
+        bot.send_message(
+            chat_id,
+            format!(
+                "🌍 Timezone: {}\n\nChange it with /timezone Europe/Berlin",
+                timezone
+            ),
+        )
+        .await?;
+
+        return Ok(());
+    }
+
+    let Ok(timezone) = name.parse::<Tz>() else {
+        bot.send_message(
+            chat_id,
+            format!(
+                "⚠️ Unknown timezone '{}', use a name like Europe/Berlin.",
+                name
+            ),
+        )
+        .await?;
+
+        return Ok(());
+    };
+
+    jobs_db.set_timezone(chat_id.0, timezone.name()).await?;
+
+    let now = chrono::Utc::now().timestamp();
+
+    for job in jobs_db.list(chat_id.0).await? {
+        if let Some(next_run) = schedule::parse(&job.schedule)
+            .ok()
+            .and_then(|schedule| schedule::next_run(&schedule, timezone, now))
+        {
+            jobs_db.set_next_run(job.id, next_run).await?;
+        }
+    }
+
+    bot.send_message(chat_id, format!("🌍 Timezone set to {}.", timezone))
+        .await?;
+
+    Ok(())
+}
+
+/// Conversation the answers of a job are kept in, created on its first run.
+async fn conversation(
+    job: &JobRow,
+    conversations_db: &ConversationsDb,
+) -> Result<ConversationRow, AppError> {
+    let name = format!("⏰ job {}", job.id);
+
+    if let Some(conversation) = conversations_db.find_by_name(job.chat_id, &name).await? {
+        return Ok(conversation);
+    }
+
+    conversations_db.create(job.chat_id, &name).await?;
+
+    conversations_db
+        .find_by_name(job.chat_id, &name)
+        .await?
+        .ok_or_else(|| AppError::InternalError("Job conversation not created.".into()))
+}
+
+pub async fn run(
+    job: JobRow,
+    bot: Bot,
+    provider: Provider,
+    db: Arc<Database>,
+    replies: ActiveReplies,
+) -> HandleResult {
+    info!("Run job, user: {}, id: {}", job.chat_id, job.id);
+
+    let conversation = conversation(&job, &db.conversations()).await?;
+
+    chat::scheduled(
+        &job.prompt,
+        bot,
+        provider,
+        &db,
+        replies,
+        ChatId(job.chat_id),
+        conversation,
+    )
+    .await
+}
@@ -0,0 +1,184 @@
+use teloxide::prelude::*;
+use tracing::info;
+
+use crate::{
+    keyboard::gpt::{create_memories_keyboard, create_memory_proposal_keyboard},
+    types::{
+        chat::{ChatRole, ChatTurn, Conversation},
+        common::{AppError, HandleResult},
+        databases::MemoriesDb,
+    },
+    utils::tokens::count_tokens,
+};
+
+pub async fn remember(
+    content: String,
+    bot: Bot,
+    chat_id: ChatId,
+    memories_db: &MemoriesDb,
+) -> HandleResult {
+    let content = content.trim();
+
+    if content.is_empty() {
+        bot.send_message(chat_id, "Usage: /remember I'm vegetarian")
+            .await?;
+
+        return Ok(());
+    }
+
+    info!("Remember, user: {}, content: {}", chat_id, content);
+
+    memories_db.add(chat_id.0, content, false).await?;
+
+    bot.send_message(chat_id, format!("🧠 Remembered: {}", content))
+        .await?;
+
+    Ok(())
+}
+
+pub async fn list(bot: Bot, chat_id: ChatId, memories_db: &MemoriesDb) -> HandleResult {
+    let memories = memories_db.list(chat_id.0).await?;
+
+    if memories.is_empty() {
+        bot.send_message(chat_id, "🧠 No memories yet, add one with /remember.")
+            .await?;
+
+        return Ok(());
+    }
+
+    let content = memories
+        .iter()
+        .map(|memory| format!("{} - {}", memory.id, memory.content))
+        .collect::<Vec<String>>()
+        .join("\n");
+
+    bot.send_message(chat_id, format!("🧠 Memories\n\n{}", content))
+        .reply_markup(create_memories_keyboard(&memories))
+        .await?;
+
+    Ok(())
+}
+
+/// Deletes a memory by its id, or the first one containing the given text.
+pub async fn forget(
+    key: String,
+    bot: Bot,
+    chat_id: ChatId,
+    memories_db: &MemoriesDb,
+) -> HandleResult {
+    let key = key.trim();
+    let id = key.parse::<i64>().unwrap_or(-1);
+    let needle = key.to_lowercase();
+
+    let memory = memories_db
+        .list(chat_id.0)
+        .await?
+        .into_iter()
+        .find(|memory| {
+            memory.id == id
+                || (!needle.is_empty() && memory.content.to_lowercase().contains(&needle))
+        });
+
+    let Some(memory) = memory else {
+        bot.send_message(chat_id, format!("⚠️ Memory '{}' not found.", key))
+            .await?;
+
+        return Ok(());
+    };
+
+    memories_db.delete(chat_id.0, memory.id).await?;
+
+    bot.send_message(chat_id, format!("🗑 Forgot: {}", memory.content))
+        .await?;
+
+    Ok(())
+}
+
+/// Stores a fact suggested by the AI as pending and asks the user to confirm it.
+pub async fn propose(
+    content: &str,
+    bot: &Bot,
+    chat_id: ChatId,
+    memories_db: &MemoriesDb,
+) -> Result<(), AppError> {
+    let id = memories_db.add(chat_id.0, content.trim(), true).await?;
+
+    bot.send_message(chat_id, format!("🧠 Remember this?\n\n{}", content.trim()))
+        .reply_markup(create_memory_proposal_keyboard(id))
+        .await?;
+
+    Ok(())
+}
+
+pub async fn confirm(id: i64, bot: Bot, chat_id: ChatId, memories_db: &MemoriesDb) -> HandleResult {
+    let Some(memory) = memories_db.get(chat_id.0, id).await? else {
+        bot.send_message(chat_id, "⚠️ Memory not found.").await?;
+
+        return Ok(());
+    };
+
+    memories_db.confirm(chat_id.0, id).await?;
+
+    bot.send_message(chat_id, format!("🧠 Remembered: {}", memory.content))
+        .await?;
+
+    Ok(())
+}
+
+pub async fn reject(id: i64, bot: Bot, chat_id: ChatId, memories_db: &MemoriesDb) -> HandleResult {
+    if !memories_db.reject(chat_id.0, id).await? {
+        bot.send_message(chat_id, "⚠️ Memory already confirmed or removed.")
+            .await?;
+
+        return Ok(());
+    }
+
+    bot.send_message(chat_id, "❌ Not remembered.").await?;
+
+    Ok(())
+}
+
+/// Prepends the chat's memories to the system prompt of the conversation.
+pub async fn apply(
+    conversation: &mut Conversation,
+    chat_id: ChatId,
+    memories_db: &MemoriesDb,
+    model: &str,
+) -> HandleResult {
+    let memories = memories_db.list(chat_id.0).await?;
+
+    if memories.is_empty() {
+        return Ok(());
+    }
+
+    let facts = memories
+        .iter()
+        .map(|memory| format!("- {}", memory.content))
+        .collect::<Vec<String>>()
+        .join("\n");
+    let block = format!("Known facts about the user:\n{}", facts);
+
+    let system = match conversation.system.take() {
+        Some(mut system) => {
+            system.content = format!("{}\n\n{}", block, system.content);
+            system.tokens += count_tokens(model, &block);
+            system
+        }
+        None => ChatTurn {
+            id: 0,
+            role: ChatRole::System,
+            tokens: count_tokens(model, &block),
+            content: block,
+            tool_calls: Vec::new(),
+            tool_call_id: None,
+            image: None,
+            created_at: chrono::Utc::now().naive_utc(),
+        },
+    };
+
+    conversation.system = Some(system);
+
+    info!("Applied {} memories, user: {}", memories.len(), chat_id);
+
+    Ok(())
+}
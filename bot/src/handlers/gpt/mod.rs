@@ -1,3 +1,16 @@
 pub mod chat;
+pub mod compare;
+pub mod context;
+pub mod conversations;
+pub mod documents;
+pub mod groups;
 pub mod history;
+pub mod jobs;
+pub mod memories;
+pub mod models;
+pub mod personas;
 pub mod prompt;
+pub mod reply;
+pub mod tools;
+pub mod transfer;
+pub mod usage;
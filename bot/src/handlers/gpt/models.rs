@@ -0,0 +1,68 @@
+use teloxide::prelude::*;
+use tracing::{info, warn};
+
+use crate::{
+    config::CONFIG,
+    keyboard::gpt::create_models_keyboard,
+    providers::Provider,
+    types::{common::HandleResult, databases::ConversationsDb},
+};
+
+pub async fn menu(
+    bot: Bot,
+    provider: Provider,
+    chat_id: ChatId,
+    conversations_db: &ConversationsDb,
+) -> HandleResult {
+    let active = conversations_db.active(chat_id.0).await?;
+    let model = CONFIG.open_ai.resolve_model(active.model.as_deref());
+    let models = CONFIG.open_ai.allowed_models();
+
+    let available = match provider.models().await {
+        Ok(available) => available,
+        Err(e) => {
+            warn!("Failed to list provider models: {:?}", e);
+            models.clone()
+        }
+    };
+
+    let keyboard = create_models_keyboard(&models, &available, &model);
+
+    bot.send_message(
+        chat_id,
+        format!("🧠 Model for '{}' (current: {}):", active.name, model),
+    )
+    .reply_markup(keyboard)
+    .await?;
+
+    Ok(())
+}
+
+pub async fn select(
+    index: String,
+    bot: Bot,
+    chat_id: ChatId,
+    conversations_db: &ConversationsDb,
+) -> HandleResult {
+    let models = CONFIG.open_ai.allowed_models();
+
+    let Some(model) = index.parse::<usize>().ok().and_then(|i| models.get(i)) else {
+        bot.send_message(chat_id, "⚠️ Unknown model.").await?;
+
+        return Ok(());
+    };
+
+    let active = conversations_db.active(chat_id.0).await?;
+
+    conversations_db.set_model(active.id, model).await?;
+
+    info!(
+        "Select model, user: {}, conversation: {}, model: {}",
+        chat_id, active.id, model
+    );
+
+    bot.send_message(chat_id, format!("🧠 '{}' now uses {}.", active.name, model))
+        .await?;
+
+    Ok(())
+}
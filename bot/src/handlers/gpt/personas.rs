@@ -0,0 +1,220 @@
+use chrono::Local;
+use teloxide::prelude::*;
+use tracing::info;
+
+use crate::{
+    config::CONFIG,
+    keyboard::gpt::create_personas_keyboard,
+    types::{
+        common::{AppError, BotDialogue, DialogueState, HandleResult},
+        databases::{ChatMessagesDb, ConversationsDb, PersonasDb},
+    },
+    utils::tokens::count_tokens,
+};
+
+fn preview(prompt: &str) -> String {
+    let line = prompt.lines().next().unwrap_or_default();
+
+    if line.chars().count() > 60 {
+        format!("{}…", line.chars().take(60).collect::<String>())
+    } else {
+        line.to_string()
+    }
+}
+
+pub async fn list(bot: Bot, chat_id: ChatId, personas_db: &PersonasDb) -> HandleResult {
+    let personas = personas_db.list(chat_id.0).await?;
+
+    let content = if personas.is_empty() {
+        "No personas yet, add one to start conversations from it.".to_string()
+    } else {
+        personas
+            .iter()
+            .map(|persona| format!("🎭 {} - {}", persona.name, preview(&persona.prompt)))
+            .collect::<Vec<String>>()
+            .join("\n")
+    };
+
+    bot.send_message(chat_id, format!("🎭 Personas\n\n{}", content))
+        .reply_markup(create_personas_keyboard(&personas))
+        .await?;
+
+    Ok(())
+}
+
+pub async fn new(bot: Bot, chat_id: ChatId, dialogue: BotDialogue) -> HandleResult {
+    bot.send_message(chat_id, "Name of the new persona:")
+        .await?;
+
+    dialogue
+        .update(DialogueState::WaitingForPersonaName)
+        .await?;
+
+    Ok(())
+}
+
+pub async fn set_name(
+    name: String,
+    bot: Bot,
+    chat_id: ChatId,
+    dialogue: BotDialogue,
+) -> HandleResult {
+    let name = name.trim().to_string();
+
+    if name.is_empty() {
+        bot.send_message(chat_id, "❌ Persona name cannot be empty")
+            .await?;
+
+        return Ok(());
+    }
+
+    bot.send_message(chat_id, format!("System prompt of '{}':", name))
+        .await?;
+
+    dialogue
+        .update(DialogueState::WaitingForPersonaPrompt { id: None, name })
+        .await?;
+
+    Ok(())
+}
+
+/// Saves the prompt as a new persona, or as the new prompt of persona `id`.
+pub async fn save(
+    id: Option<i64>,
+    name: String,
+    prompt: String,
+    bot: Bot,
+    chat_id: ChatId,
+    personas_db: &PersonasDb,
+) -> HandleResult {
+    let prompt = prompt.trim();
+
+    if prompt.is_empty() {
+        bot.send_message(chat_id, "❌ Persona prompt cannot be empty")
+            .await?;
+
+        return Ok(());
+    }
+
+    info!("Save persona, user: {}, name: {}", chat_id, name);
+
+    match id {
+        Some(id) => {
+            personas_db.set_prompt(chat_id.0, id, prompt).await?;
+        }
+        None => match personas_db.create(chat_id.0, &name, prompt).await {
+            Ok(_) => {}
+            Err(AppError::InternalError(message)) => {
+                bot.send_message(chat_id, format!("⚠️ {}", message)).await?;
+
+                return Ok(());
+            }
+            Err(e) => return Err(e),
+        },
+    }
+
+    bot.send_message(chat_id, format!("🎭 Persona '{}' saved.", name))
+        .await?;
+
+    list(bot, chat_id, personas_db).await
+}
+
+pub async fn edit(
+    id: i64,
+    bot: Bot,
+    chat_id: ChatId,
+    dialogue: BotDialogue,
+    personas_db: &PersonasDb,
+) -> HandleResult {
+    let Some(persona) = personas_db.get(chat_id.0, id).await? else {
+        bot.send_message(chat_id, "⚠️ Persona not found.").await?;
+
+        return Ok(());
+    };
+
+    bot.send_message(
+        chat_id,
+        format!(
+            "Current prompt of '{}':\n\n{}\n\nSend the new prompt:",
+            persona.name, persona.prompt
+        ),
+    )
+    .await?;
+
+    dialogue
+        .update(DialogueState::WaitingForPersonaPrompt {
+            id: Some(persona.id),
+            name: persona.name,
+        })
+        .await?;
+
+    Ok(())
+}
+
+pub async fn delete(id: i64, bot: Bot, chat_id: ChatId, personas_db: &PersonasDb) -> HandleResult {
+    let Some(persona) = personas_db.get(chat_id.0, id).await? else {
+        bot.send_message(chat_id, "⚠️ Persona not found.").await?;
+
+        return Ok(());
+    };
+
+    personas_db.delete(chat_id.0, persona.id).await?;
+
+    bot.send_message(chat_id, format!("🗑 Deleted persona '{}'.", persona.name))
+        .await?;
+
+    Ok(())
+}
+
+/// Starts a new conversation named after the persona, with its prompt as system prompt.
+pub async fn start(
+    id: i64,
+    bot: Bot,
+    chat_id: ChatId,
+    personas_db: &PersonasDb,
+    conversations_db: &ConversationsDb,
+    messages_db: &ChatMessagesDb,
+) -> HandleResult {
+    let Some(persona) = personas_db.get(chat_id.0, id).await? else {
+        bot.send_message(chat_id, "⚠️ Persona not found.").await?;
+
+        return Ok(());
+    };
+
+    info!("Start persona, user: {}, name: {}", chat_id, persona.name);
+
+    let name = match conversations_db
+        .find_by_name(chat_id.0, &persona.name)
+        .await?
+    {
+        Some(_) => format!(
+            "{} {}",
+            persona.name,
+            Local::now().format("%Y-%m-%d %H:%M:%S")
+        ),
+        None => persona.name.clone(),
+    };
+
+    let conversation_id = conversations_db.create(chat_id.0, &name).await?;
+
+    conversations_db
+        .set_active(chat_id.0, conversation_id)
+        .await?;
+
+    messages_db
+        .set_system(
+            chat_id.0,
+            conversation_id,
+            &persona.prompt,
+            count_tokens(&CONFIG.open_ai.resolve_model(None), &persona.prompt),
+        )
+        .await?;
+
+    bot.send_message(
+        chat_id,
+        format!("🎭 Started conversation '{}' as {}.", name, persona.name),
+    )
+    .await?;
+
+    Ok(())
+}
@@ -1,27 +1,39 @@
-use async_openai::types::ChatCompletionRequestUserMessageArgs;
-
 use teloxide::{prelude::*, sugar::request::RequestReplyExt};
 use tracing::info;
 
-use crate::types::common::{ChatHistoryState, HandleResult};
+use crate::{
+    config::CONFIG,
+    types::{
+        common::HandleResult,
+        databases::{ChatMessagesDb, ConversationsDb},
+    },
+    utils::tokens::count_tokens,
+};
 
-pub async fn set(prompt: String, bot: Bot, state: ChatHistoryState, msg: Message) -> HandleResult {
+pub async fn set(
+    prompt: String,
+    bot: Bot,
+    conversations_db: &ConversationsDb,
+    messages_db: &ChatMessagesDb,
+    msg: Message,
+) -> HandleResult {
     info!("Set prompt, user: {}, prompt: {}", msg.chat.id, prompt);
 
-    {
-        let mut guard = state.lock().unwrap();
-        let messages = guard.entry(msg.chat.id).or_default();
+    let active = conversations_db.active(msg.chat.id.0).await?;
 
-        messages.clear();
-        messages.push(
-            ChatCompletionRequestUserMessageArgs::default()
-                .content(prompt)
-                .build()?
-                .into(),
-        );
-    }
+    messages_db
+        .set_system(
+            msg.chat.id.0,
+            active.id,
+            &prompt,
+            count_tokens(
+                &CONFIG.open_ai.resolve_model(active.model.as_deref()),
+                &prompt,
+            ),
+        )
+        .await?;
 
-    bot.send_message(msg.chat.id, "Prompt set.")
+    bot.send_message(msg.chat.id, "System prompt set.")
         .reply_to(msg.id)
         .await?;
 
@@ -0,0 +1,185 @@
+use std::time::{Duration, Instant};
+use teloxide::{
+    prelude::*,
+    types::{InlineKeyboardMarkup, MessageId, ParseMode},
+    RequestError,
+};
+use tracing::warn;
+
+use crate::{
+    types::common::HandleResult,
+    utils::markdown::{markdown_to_telegram_v2, split_markdown, MESSAGE_LIMIT},
+};
+
+/// Minimal time between edits of a streamed reply, Telegram rate limits message edits.
+const EDIT_INTERVAL: Duration = Duration::from_millis(1200);
+
+/// Renders a streamed reply into the placeholder message, rolling over into
+/// follow-up messages when the reply outgrows the Telegram message limit.
+pub struct ReplyWriter {
+    bot: Bot,
+    chat_id: ChatId,
+    messages: Vec<MessageId>,
+    rendered: Vec<String>,
+    markup: Option<InlineKeyboardMarkup>,
+    last_edit: Instant,
+}
+
+impl ReplyWriter {
+    pub fn new(bot: Bot, chat_id: ChatId, placeholder: MessageId) -> Self {
+        Self {
+            bot,
+            chat_id,
+            messages: vec![placeholder],
+            rendered: vec![String::new()],
+            markup: None,
+            last_edit: Instant::now(),
+        }
+    }
+
+    /// Telegram messages the reply has been rendered into so far.
+    pub fn messages(&self) -> &[MessageId] {
+        &self.messages
+    }
+
+    /// Renders the reply so far, skipped when the previous edit was too recent.
+    pub async fn update(&mut self, text: &str) -> HandleResult {
+        if self.last_edit.elapsed() < EDIT_INTERVAL {
+            return Ok(());
+        }
+
+        self.render(text).await
+    }
+
+    /// Renders the complete reply regardless of the edit interval.
+    pub async fn finish(&mut self, text: &str) -> HandleResult {
+        self.render(text).await
+    }
+
+    /// Attaches inline buttons to the first message, they are kept across edits.
+    pub async fn set_markup(&mut self, markup: InlineKeyboardMarkup) -> HandleResult {
+        self.bot
+            .edit_message_reply_markup(self.chat_id, self.messages[0])
+            .reply_markup(markup.clone())
+            .await?;
+
+        self.markup = Some(markup);
+
+        Ok(())
+    }
+
+    /// Removes the inline buttons of the first message.
+    pub async fn clear_markup(&mut self) -> HandleResult {
+        self.bot
+            .edit_message_reply_markup(self.chat_id, self.messages[0])
+            .await?;
+
+        self.markup = None;
+
+        Ok(())
+    }
+
+    /// Replaces the first message with a plain status line, e.g. while tools run.
+    pub async fn status(&mut self, text: &str) -> HandleResult {
+        let request = self
+            .bot
+            .edit_message_text(self.chat_id, self.messages[0], text);
+
+        match self.markup.clone() {
+            Some(markup) => request.reply_markup(markup).await?,
+            None => request.await?,
+        };
+
+        self.rendered[0] = text.to_string();
+        self.last_edit = Instant::now();
+
+        Ok(())
+    }
+
+    async fn render(&mut self, text: &str) -> HandleResult {
+        let parts = split_markdown(text, MESSAGE_LIMIT);
+        let count = parts.len().max(1);
+
+        for (index, part) in parts.into_iter().enumerate() {
+            if part.trim().is_empty() || self.rendered.get(index) == Some(&part) {
+                continue;
+            }
+
+            match self.messages.get(index) {
+                Some(&message_id) => self.edit(message_id, &part).await?,
+                None => {
+                    let message_id = self.send(&part).await?;
+
+                    self.messages.push(message_id);
+                    self.rendered.push(String::new());
+                }
+            }
+
+            self.rendered[index] = part;
+        }
+
+        // A reply that shrank, e.g. the final text replacing a long draft, leaves
+        // rolled over messages behind.
+        for &message_id in self.messages.iter().skip(count) {
+            if let Err(e) = self.bot.delete_message(self.chat_id, message_id).await {
+                warn!("Failed to delete surplus reply message: {:?}", e);
+            }
+        }
+
+        self.messages.truncate(count);
+        self.rendered.truncate(count);
+        self.last_edit = Instant::now();
+
+        Ok(())
+    }
+
+    async fn edit(&self, message_id: MessageId, part: &str) -> Result<(), RequestError> {
+        let markup = if message_id == self.messages[0] {
+            self.markup.clone()
+        } else {
+            None
+        };
+
+        let formatted = self
+            .bot
+            .edit_message_text(self.chat_id, message_id, markdown_to_telegram_v2(part))
+            .parse_mode(ParseMode::MarkdownV2);
+
+        let result = match markup.clone() {
+            Some(markup) => formatted.reply_markup(markup).await,
+            None => formatted.await,
+        };
+
+        if let Err(e) = result {
+            warn!("MarkdownV2 edit rejected, sending plain text: {:?}", e);
+
+            let plain = self.bot.edit_message_text(self.chat_id, message_id, part);
+
+            match markup {
+                Some(markup) => plain.reply_markup(markup).await?,
+                None => plain.await?,
+            };
+        }
+
+        Ok(())
+    }
+
+    async fn send(&self, part: &str) -> Result<MessageId, RequestError> {
+        let formatted = self
+            .bot
+            .send_message(self.chat_id, markdown_to_telegram_v2(part))
+            .parse_mode(ParseMode::MarkdownV2)
+            .await;
+
+        let message = match formatted {
+            Ok(message) => message,
+            Err(e) => {
+                warn!("MarkdownV2 message rejected, sending plain text: {:?}", e);
+
+                self.bot.send_message(self.chat_id, part).await?
+            }
+        };
+
+        Ok(message.id)
+    }
+}
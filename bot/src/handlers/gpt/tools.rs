@@ -0,0 +1,264 @@
+use async_openai::types::{ChatCompletionRequestMessage, ChatCompletionRequestSystemMessageArgs};
+use chrono::NaiveDate;
+use chrono_tz::Tz;
+use serde::Deserialize;
+use serde_json::{json, Value};
+use std::str::FromStr;
+use teloxide::prelude::*;
+use tracing::{info, warn};
+
+use crate::{
+    handlers::gpt::memories,
+    providers::ToolDefinition,
+    types::{
+        chat::ToolCall,
+        common::{AppError, TransactionKind},
+        databases::Database,
+        models::TransactionRow,
+    },
+};
+
+/// Transactions returned to the model per call, totals still cover every match.
+const MAX_TOOL_ROWS: usize = 100;
+const DEFAULT_SEARCH_LIMIT: i64 = 10;
+const MAX_SEARCH_LIMIT: i64 = 50;
+
+#[derive(Deserialize)]
+struct ListTransactionsArgs {
+    start_date: Option<NaiveDate>,
+    end_date: Option<NaiveDate>,
+    category: Option<String>,
+    kind: Option<String>,
+}
+
+#[derive(Deserialize)]
+struct SearchTransactionsArgs {
+    query: String,
+    limit: Option<i64>,
+}
+
+#[derive(Deserialize)]
+struct ListCategoriesArgs {
+    kind: String,
+}
+
+#[derive(Deserialize)]
+struct ProposeMemoryArgs {
+    fact: String,
+}
+
+/// Tools offered to the model, all read-only except memory proposals the user confirms.
+pub fn definitions() -> Vec<ToolDefinition> {
+    vec![
+        ToolDefinition {
+            name: "list_transactions",
+            description: "List the user's transactions in a date range with totals. \
+                Amounts are in euros, spending is negative.",
+            parameters: json!({
+                "type": "object",
+                "properties": {
+                    "start_date": { "type": "string", "description": "Inclusive start date, YYYY-MM-DD." },
+                    "end_date": { "type": "string", "description": "Inclusive end date, YYYY-MM-DD." },
+                    "category": { "type": "string", "description": "Only transactions of this category name." },
+                    "kind": { "type": "string", "enum": ["income", "spending"] }
+                }
+            }),
+        },
+        ToolDefinition {
+            name: "search_transactions",
+            description: "Search the user's transactions by description text.",
+            parameters: json!({
+                "type": "object",
+                "properties": {
+                    "query": { "type": "string" },
+                    "limit": { "type": "integer", "minimum": 1, "maximum": MAX_SEARCH_LIMIT }
+                },
+                "required": ["query"]
+            }),
+        },
+        ToolDefinition {
+            name: "list_categories",
+            description: "List budgeting categories of a kind.",
+            parameters: json!({
+                "type": "object",
+                "properties": {
+                    "kind": { "type": "string", "enum": ["income", "spending"] }
+                },
+                "required": ["kind"]
+            }),
+        },
+        ToolDefinition {
+            name: "propose_memory",
+            description: "Propose to remember a lasting fact or preference about the user \
+                for future conversations. The user has to confirm it.",
+            parameters: json!({
+                "type": "object",
+                "properties": {
+                    "fact": { "type": "string", "description": "Short fact in third person." }
+                },
+                "required": ["fact"]
+            }),
+        },
+    ]
+}
+
+fn parse_args<'a, T: Deserialize<'a>>(call: &'a ToolCall) -> Result<T, AppError> {
+    serde_json::from_str(&call.arguments)
+        .map_err(|e| AppError::InternalError(format!("Invalid arguments for {}: {}", call.name, e)))
+}
+
+fn parse_kind(kind: &str) -> Result<TransactionKind, AppError> {
+    TransactionKind::from_str(kind)
+        .map_err(|_| AppError::InternalError(format!("Unknown transaction kind: {}", kind)))
+}
+
+fn euros(amount: i64) -> f64 {
+    amount as f64 / 100.0
+}
+
+fn transaction_json(row: &TransactionRow) -> Value {
+    json!({
+        "id": row.id,
+        "date": row.date.format("%Y-%m-%d").to_string(),
+        "amount": euros(row.amount),
+        "category": row.category_name,
+        "description": row.description,
+    })
+}
+
+async fn list_transactions(
+    call: &ToolCall,
+    user_id: i64,
+    db: &Database,
+) -> Result<Value, AppError> {
+    let args: ListTransactionsArgs = parse_args(call)?;
+    let kind = args.kind.as_deref().map(parse_kind).transpose()?;
+
+    let rows: Vec<TransactionRow> = db
+        .transactions()
+        .list_with_range(user_id, args.start_date, args.end_date)
+        .await
+        .into_iter()
+        .filter(|row| {
+            args.category
+                .as_ref()
+                .is_none_or(|category| row.category_name.eq_ignore_ascii_case(category))
+        })
+        .filter(|row| match kind {
+            Some(TransactionKind::Income) => row.amount > 0,
+            Some(TransactionKind::Spending) => row.amount < 0,
+            None => true,
+        })
+        .collect();
+
+    let income: i64 = rows.iter().map(|row| row.amount).filter(|a| *a > 0).sum();
+    let spending: i64 = rows.iter().map(|row| row.amount).filter(|a| *a < 0).sum();
+
+    Ok(json!({
+        "count": rows.len(),
+        "income_total": euros(income),
+        "spending_total": euros(spending),
+        "truncated": rows.len() > MAX_TOOL_ROWS,
+        "transactions": rows.iter().take(MAX_TOOL_ROWS).map(transaction_json).collect::<Vec<_>>(),
+    }))
+}
+
+async fn search_transactions(
+    call: &ToolCall,
+    user_id: i64,
+    db: &Database,
+) -> Result<Value, AppError> {
+    let args: SearchTransactionsArgs = parse_args(call)?;
+    let limit = args
+        .limit
+        .unwrap_or(DEFAULT_SEARCH_LIMIT)
+        .clamp(1, MAX_SEARCH_LIMIT);
+
+    let rows = db
+        .transactions()
+        .search_by_description(user_id, &args.query, limit)
+        .await;
+
+    Ok(json!({
+        "count": rows.len(),
+        "transactions": rows.iter().map(transaction_json).collect::<Vec<_>>(),
+    }))
+}
+
+async fn list_categories(call: &ToolCall, db: &Database) -> Result<Value, AppError> {
+    let args: ListCategoriesArgs = parse_args(call)?;
+    let categories = db.categories().list(parse_kind(&args.kind)?).await;
+
+    Ok(json!({
+        "categories": categories
+            .iter()
+            .map(|category| json!({ "id": category.id, "name": category.name }))
+            .collect::<Vec<_>>(),
+    }))
+}
+
+async fn propose_memory(
+    call: &ToolCall,
+    bot: &Bot,
+    chat_id: ChatId,
+    db: &Database,
+) -> Result<Value, AppError> {
+    let args: ProposeMemoryArgs = parse_args(call)?;
+
+    if args.fact.trim().is_empty() {
+        return Err(AppError::InternalError("Empty fact".to_string()));
+    }
+
+    memories::propose(&args.fact, bot, chat_id, &db.memories()).await?;
+
+    Ok(json!({ "status": "asked the user to confirm" }))
+}
+
+/// Tools take absolute dates, the model needs today's date in the user's timezone to
+/// resolve "yesterday" or "the last three months".
+pub fn date_context(timezone: Tz) -> Result<ChatCompletionRequestMessage, AppError> {
+    let today = chrono::Utc::now().with_timezone(&timezone);
+
+    Ok(ChatCompletionRequestSystemMessageArgs::default()
+        .content(format!(
+            "Today is {}, {} ({}).",
+            today.format("%A"),
+            today.format("%Y-%m-%d"),
+            timezone
+        ))
+        .build()?
+        .into())
+}
+
+/// Runs a tool call and returns its JSON result, failures are reported to the model as errors.
+pub async fn execute(call: &ToolCall, bot: &Bot, chat_id: ChatId, db: &Database) -> String {
+    let user_id = chat_id.0;
+
+    info!(
+        "Tool call, user: {}, name: {}, arguments: {}",
+        user_id, call.name, call.arguments
+    );
+
+    let result = match call.name.as_str() {
+        "list_transactions" => list_transactions(call, user_id, db).await,
+        "search_transactions" => search_transactions(call, user_id, db).await,
+        "list_categories" => list_categories(call, db).await,
+        "propose_memory" => propose_memory(call, bot, chat_id, db).await,
+        name => Err(AppError::InternalError(format!("Unknown tool: {}", name))),
+    };
+
+    match result {
+        Ok(value) => value.to_string(),
+        Err(e) => {
+            warn!("Tool call failed: {:?}", e);
+
+            // The model needs the detail to correct its call, not the generic display text.
+            let message = match e {
+                AppError::InternalError(message) => message,
+                e => e.to_string(),
+            };
+
+            json!({ "error": message }).to_string()
+        }
+    }
+}
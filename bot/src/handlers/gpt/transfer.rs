@@ -0,0 +1,422 @@
+use std::{str::FromStr, sync::Arc};
+use teloxide::{
+    prelude::*,
+    sugar::request::RequestReplyExt,
+    types::{Document, InputFile},
+};
+use tracing::{info, warn};
+
+use crate::{
+    config::CONFIG,
+    databases::gpt::conversations::validate_name,
+    types::{
+        chat::{ChatRole, ConversationExport, ExportedMessage, EXPORT_VERSION},
+        common::{AppError, BotDialogue, DialogueState, HandleResult},
+        databases::{ChatMessagesDb, ConversationsDb, Database},
+    },
+    utils::{files::download, tokens::count_tokens},
+};
+
+const MAX_IMPORT_SIZE: u32 = 20 * 1024 * 1024;
+
+const KINDS: [&str; 2] = ["message", "summary"];
+
+/// Keeps letters, digits, dashes and underscores of the conversation name for a file name.
+fn file_stem(name: &str) -> String {
+    let stem: String = name
+        .chars()
+        .map(|c| {
+            if c.is_alphanumeric() || c == '-' || c == '_' {
+                c
+            } else {
+                '_'
+            }
+        })
+        .collect();
+
+    match stem.trim_matches('_') {
+        "" => "conversation".to_string(),
+        stem => stem.to_string(),
+    }
+}
+
+fn heading(message: &ExportedMessage) -> &'static str {
+    match (message.role.as_str(), message.kind.as_str()) {
+        ("system", "summary") => "Summary",
+        ("system", _) => "System prompt",
+        ("user", _) => "User",
+        ("assistant", _) => "Assistant",
+        ("tool", _) => "Tool result",
+        _ => "Message",
+    }
+}
+
+fn markdown(export: &ConversationExport) -> String {
+    let mut lines = vec![
+        format!("# {}", export.name),
+        String::new(),
+        format!(
+            "- Model: {}",
+            CONFIG.open_ai.resolve_model(export.model.as_deref())
+        ),
+        format!(
+            "- Exported: {} UTC",
+            export.exported_at.format("%Y-%m-%d %H:%M")
+        ),
+        format!("- Messages: {}", export.messages.len()),
+    ];
+
+    for message in &export.messages {
+        lines.push(String::new());
+        lines.push(format!(
+            "## {} · {}",
+            heading(message),
+            message.created_at.format("%Y-%m-%d %H:%M")
+        ));
+        lines.push(String::new());
+
+        if message.image.is_some() {
+            lines.push("_[image]_".to_string());
+            lines.push(String::new());
+        }
+
+        match message.role.as_str() {
+            "tool" => lines.push(format!("```json\n{}\n```", message.content)),
+            _ if !message.content.is_empty() => lines.push(message.content.clone()),
+            _ => {}
+        }
+
+        for call in &message.tool_calls {
+            lines.push(format!("🛠 `{}({})`", call.name, call.arguments));
+        }
+    }
+
+    lines.join("\n")
+}
+
+pub async fn export(
+    format: String,
+    bot: Bot,
+    chat_id: ChatId,
+    conversations_db: &ConversationsDb,
+    messages_db: &ChatMessagesDb,
+) -> HandleResult {
+    let format = format.trim().to_lowercase();
+
+    if !["", "md", "markdown", "json"].contains(&format.as_str()) {
+        bot.send_message(chat_id, "Usage: /export md or /export json")
+            .await?;
+
+        return Ok(());
+    }
+
+    let active = conversations_db.active(chat_id.0).await?;
+    let messages = messages_db
+        .list(active.id)
+        .await?
+        .into_iter()
+        .map(ExportedMessage::try_from)
+        .collect::<Result<Vec<ExportedMessage>, AppError>>()?;
+
+    info!(
+        "Export conversation, user: {}, conversation: {}, format: {}",
+        chat_id, active.id, format
+    );
+
+    let export = ConversationExport {
+        version: EXPORT_VERSION,
+        name: active.name.clone(),
+        model: active.model.clone(),
+        exported_at: chrono::Utc::now().naive_utc(),
+        messages,
+    };
+
+    let (content, extension) = match format.as_str() {
+        "json" => (serde_json::to_string_pretty(&export)?, "json"),
+        _ => (markdown(&export), "md"),
+    };
+
+    let file = InputFile::memory(content.into_bytes()).file_name(format!(
+        "{}.{}",
+        file_stem(&active.name),
+        extension
+    ));
+
+    bot.send_document(chat_id, file)
+        .caption(format!("📤 {}", active.name))
+        .await?;
+
+    Ok(())
+}
+
+pub async fn start_import(bot: Bot, chat_id: ChatId, dialogue: BotDialogue) -> HandleResult {
+    dialogue
+        .update(DialogueState::WaitingForConversationImport)
+        .await?;
+
+    bot.send_message(
+        chat_id,
+        "📥 Send the JSON file created by /export json to restore it as a new conversation.",
+    )
+    .await?;
+
+    Ok(())
+}
+
+/// First free name among `name`, `name (imported)`, `name (imported 2)`...
+/// Name of the imported conversation: empty names get a default one, numeric or taken
+/// ones are suffixed until they are valid and free.
+async fn free_name(
+    chat_id: ChatId,
+    name: &str,
+    conversations_db: &ConversationsDb,
+) -> Result<String, AppError> {
+    let name = match name.trim() {
+        "" => "Imported conversation",
+        name => name,
+    };
+    let mut candidate = name.to_string();
+    let mut index = 1;
+
+    while validate_name(&candidate).is_err()
+        || conversations_db
+            .find_by_name(chat_id.0, &candidate)
+            .await?
+            .is_some()
+    {
+        candidate = match index {
+            1 => format!("{} (imported)", name),
+            _ => format!("{} (imported {})", name, index),
+        };
+        index += 1;
+    }
+
+    Ok(candidate)
+}
+
+/// Checks roles and kinds, that every tool call has its result and every result its call,
+/// and recounts tokens for the configured model.
+fn prepare(export: &mut ConversationExport, model: &str) -> Result<(), String> {
+    if export.version > EXPORT_VERSION {
+        return Err(format!(
+            "Export version {} is newer than the supported {}.",
+            export.version, EXPORT_VERSION
+        ));
+    }
+
+    // Calls of the latest assistant turn still waiting for their result.
+    let mut pending: Vec<String> = Vec::new();
+
+    for message in export.messages.iter_mut() {
+        let role = ChatRole::from_str(&message.role)
+            .map_err(|_| format!("Unknown role '{}'.", message.role))?;
+
+        if !KINDS.contains(&message.kind.as_str()) {
+            return Err(format!("Unknown message kind '{}'.", message.kind));
+        }
+
+        if message.kind == "summary" && role != ChatRole::System {
+            return Err(format!(
+                "A summary cannot have the role '{}'.",
+                message.role
+            ));
+        }
+
+        match role {
+            ChatRole::Tool => {
+                let id = message.tool_call_id.as_deref().unwrap_or_default();
+                let Some(index) = pending.iter().position(|call| *call == id) else {
+                    return Err(format!("Tool result '{}' has no matching tool call.", id));
+                };
+
+                pending.remove(index);
+            }
+            _ if !pending.is_empty() => {
+                return Err(format!("Tool call '{}' has no result.", pending[0]));
+            }
+            ChatRole::Assistant => {
+                pending = message
+                    .tool_calls
+                    .iter()
+                    .map(|call| call.id.clone())
+                    .collect();
+            }
+            _ => {}
+        }
+
+        message.tokens = count_tokens(model, &message.content) as i64;
+    }
+
+    if let Some(call) = pending.first() {
+        return Err(format!("Tool call '{}' has no result.", call));
+    }
+
+    Ok(())
+}
+
+async fn read_export(bot: &Bot, document: &Document) -> Result<ConversationExport, String> {
+    if document.file.size > MAX_IMPORT_SIZE {
+        return Err("The file is larger than 20 MB.".to_string());
+    }
+
+    let data = download(bot, &document.file.id)
+        .await
+        .map_err(|e| format!("Download failed: {}", e))?;
+
+    serde_json::from_slice(&data).map_err(|e| format!("Not a conversation export: {}", e))
+}
+
+pub async fn import(
+    db: Arc<Database>,
+    bot: Bot,
+    dialogue: BotDialogue,
+    msg: Message,
+) -> HandleResult {
+    let Some(document) = msg.document() else {
+        return Ok(());
+    };
+
+    dialogue.update(DialogueState::Start).await?;
+
+    let conversations_db = db.conversations();
+    let model = CONFIG.open_ai.resolve_model(None);
+
+    let mut export = match read_export(&bot, document).await {
+        Ok(export) => export,
+        Err(message) => {
+            bot.send_message(msg.chat.id, format!("⚠️ {}", message))
+                .reply_to(msg.id)
+                .await?;
+
+            return Ok(());
+        }
+    };
+
+    if let Err(message) = prepare(&mut export, &model) {
+        bot.send_message(msg.chat.id, format!("⚠️ {}", message))
+            .reply_to(msg.id)
+            .await?;
+
+        return Ok(());
+    }
+
+    let name = free_name(msg.chat.id, export.name.trim(), &conversations_db).await?;
+    let id = conversations_db.create(msg.chat.id.0, &name).await?;
+
+    if let Err(e) = db
+        .chat_messages()
+        .import(msg.chat.id.0, id, &export.messages)
+        .await
+    {
+        warn!("Failed to import conversation: {:?}", e);
+
+        conversations_db.delete(id).await?;
+
+        return Err(e.into());
+    }
+
+    if let Some(model) = export
+        .model
+        .as_ref()
+        .filter(|model| CONFIG.open_ai.models.contains(model))
+    {
+        conversations_db.set_model(id, model).await?;
+    }
+
+    conversations_db.set_active(msg.chat.id.0, id).await?;
+
+    info!(
+        "Import conversation, user: {}, conversation: {}, messages: {}",
+        msg.chat.id,
+        id,
+        export.messages.len()
+    );
+
+    bot.send_message(
+        msg.chat.id,
+        format!(
+            "📥 Imported '{}' with {} messages, it is now active.",
+            name,
+            export.messages.len()
+        ),
+    )
+    .reply_to(msg.id)
+    .await?;
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::types::chat::ToolCall;
+
+    fn message(role: &str, kind: &str) -> ExportedMessage {
+        ExportedMessage {
+            role: role.to_string(),
+            kind: kind.to_string(),
+            content: "text".to_string(),
+            tokens: 0,
+            tool_calls: Vec::new(),
+            tool_call_id: None,
+            image: None,
+            created_at: chrono::Utc::now().naive_utc(),
+        }
+    }
+
+    fn call(id: &str) -> ExportedMessage {
+        ExportedMessage {
+            tool_calls: vec![ToolCall {
+                id: id.to_string(),
+                name: "list_categories".to_string(),
+                arguments: "{}".to_string(),
+            }],
+            ..message("assistant", "message")
+        }
+    }
+
+    fn result(id: &str) -> ExportedMessage {
+        ExportedMessage {
+            tool_call_id: Some(id.to_string()),
+            ..message("tool", "message")
+        }
+    }
+
+    fn prepared(messages: Vec<ExportedMessage>) -> Result<(), String> {
+        let mut export = ConversationExport {
+            version: EXPORT_VERSION,
+            name: "test".to_string(),
+            model: None,
+            exported_at: chrono::Utc::now().naive_utc(),
+            messages,
+        };
+
+        prepare(&mut export, "mock")
+    }
+
+    #[test]
+    fn accepts_paired_tool_calls_and_system_summaries() {
+        let messages = vec![
+            message("system", "summary"),
+            message("user", "message"),
+            call("call-1"),
+            result("call-1"),
+            message("assistant", "message"),
+        ];
+
+        assert_eq!(prepared(messages), Ok(()));
+    }
+
+    #[test]
+    fn rejects_unpaired_tool_messages() {
+        assert!(prepared(vec![message("user", "message"), result("call-1")]).is_err());
+        assert!(prepared(vec![call("call-1"), message("user", "message")]).is_err());
+        assert!(prepared(vec![call("call-1"), result("call-2")]).is_err());
+        assert!(prepared(vec![call("call-1")]).is_err());
+    }
+
+    #[test]
+    fn rejects_summaries_outside_the_system_role() {
+        assert!(prepared(vec![message("assistant", "summary")]).is_err());
+    }
+}
@@ -0,0 +1,205 @@
+use chrono::{Datelike, Local, NaiveDate};
+use num_format::{Locale, ToFormattedString};
+use std::time::Instant;
+use teloxide::prelude::*;
+use tracing::{info, warn};
+
+use crate::{
+    config::CONFIG,
+    providers::TokenUsage,
+    types::{
+        common::{AppError, ChatMessages, HandleResult},
+        databases::UsageDb,
+        models::UsageTotalRow,
+    },
+    utils::tokens::count_tokens,
+};
+
+fn start_of(date: NaiveDate) -> i64 {
+    date.and_hms_opt(0, 0, 0)
+        .and_then(|start| start.and_local_timezone(Local).earliest())
+        .map(|start| start.timestamp())
+        .unwrap_or_default()
+}
+
+fn today_start() -> i64 {
+    start_of(Local::now().date_naive())
+}
+
+fn month_start() -> i64 {
+    let today = Local::now().date_naive();
+
+    start_of(today.with_day(1).unwrap_or(today))
+}
+
+fn tokens(value: i64) -> String {
+    value.to_formatted_string(&Locale::en)
+}
+
+/// Token counts computed locally for backends that do not report usage.
+pub fn estimate(model: &str, messages: &ChatMessages, reply: &str) -> TokenUsage {
+    let prompt = serde_json::to_string(messages).unwrap_or_default();
+
+    TokenUsage {
+        prompt_tokens: count_tokens(model, &prompt) as u32,
+        completion_tokens: count_tokens(model, reply) as u32,
+    }
+}
+
+/// Token counts of embedding `inputs`, for backends that do not report usage.
+pub fn estimate_embedding(model: &str, inputs: &[String]) -> TokenUsage {
+    TokenUsage {
+        prompt_tokens: inputs
+            .iter()
+            .map(|input| count_tokens(model, input) as u32)
+            .sum(),
+        completion_tokens: 0,
+    }
+}
+
+/// Stores usage of one completion, failures are logged without failing the reply.
+pub async fn record(
+    usage_db: &UsageDb,
+    chat_id: ChatId,
+    model: &str,
+    usage: TokenUsage,
+    started: Instant,
+) {
+    let latency_ms = started.elapsed().as_millis() as i64;
+
+    info!(
+        "Usage, user: {}, model: {}, prompt: {}, completion: {}, latency: {}ms",
+        chat_id, model, usage.prompt_tokens, usage.completion_tokens, latency_ms
+    );
+
+    if let Err(e) = usage_db
+        .add(
+            chat_id.0,
+            model,
+            usage.prompt_tokens,
+            usage.completion_tokens,
+            latency_ms,
+        )
+        .await
+    {
+        warn!("Failed to record usage: {:?}", e);
+    }
+}
+
+/// First used up quota as its period, size and the tokens spent in it.
+async fn exceeded_quota(
+    usage_db: &UsageDb,
+    chat_id: ChatId,
+) -> Result<Option<(&'static str, u64, i64)>, AppError> {
+    let config = &CONFIG.usage;
+    let limits = [
+        ("daily", config.daily_token_quota, today_start()),
+        ("monthly", config.monthly_token_quota, month_start()),
+    ];
+
+    for (period, quota, since) in limits {
+        let Some(quota) = quota else {
+            continue;
+        };
+
+        let spent = usage_db.tokens_since(chat_id.0, since).await?;
+
+        if spent as u64 >= quota {
+            return Ok(Some((period, quota, spent)));
+        }
+    }
+
+    Ok(None)
+}
+
+/// Whether the chat has tokens left in both its daily and monthly quota.
+pub async fn within_quota(usage_db: &UsageDb, chat_id: ChatId) -> Result<bool, AppError> {
+    Ok(exceeded_quota(usage_db, chat_id).await?.is_none())
+}
+
+/// Tells the user and returns false once the daily or monthly token quota is spent.
+pub async fn check_quota(bot: &Bot, usage_db: &UsageDb, chat_id: ChatId) -> Result<bool, AppError> {
+    let Some((period, quota, spent)) = exceeded_quota(usage_db, chat_id).await? else {
+        return Ok(true);
+    };
+
+    bot.send_message(
+        chat_id,
+        format!(
+            "⛔ The {} AI quota of {} tokens is used up ({} spent).",
+            period,
+            tokens(quota as i64),
+            tokens(spent)
+        ),
+    )
+    .await?;
+
+    Ok(false)
+}
+
+fn summary(label: &str, rows: &[UsageTotalRow], quota: Option<u64>) -> String {
+    let requests: i64 = rows.iter().map(|row| row.requests).sum();
+    let total: i64 = rows
+        .iter()
+        .map(|row| row.prompt_tokens + row.completion_tokens)
+        .sum();
+    let cost: f64 = rows
+        .iter()
+        .filter_map(|row| {
+            CONFIG
+                .usage
+                .cost(&row.model, row.prompt_tokens, row.completion_tokens)
+        })
+        .sum();
+
+    let quota = quota
+        .map(|quota| format!(" of {}", tokens(quota as i64)))
+        .unwrap_or_default();
+
+    format!(
+        "{}: {}{} tokens, {} requests ≈ ${:.2}",
+        label,
+        tokens(total),
+        quota,
+        requests,
+        cost
+    )
+}
+
+pub async fn report(bot: Bot, usage_db: &UsageDb, chat_id: ChatId) -> HandleResult {
+    let today = usage_db.totals_since(chat_id.0, today_start()).await?;
+    let month = usage_db.totals_since(chat_id.0, month_start()).await?;
+
+    let mut lines = vec![
+        "📈 AI usage".to_string(),
+        String::new(),
+        summary("Today", &today, CONFIG.usage.daily_token_quota),
+        summary("This month", &month, CONFIG.usage.monthly_token_quota),
+    ];
+
+    if !month.is_empty() {
+        lines.push(String::new());
+        lines.push("By model this month:".to_string());
+    }
+
+    for row in &month {
+        let cost = CONFIG
+            .usage
+            .cost(&row.model, row.prompt_tokens, row.completion_tokens)
+            .map(|cost| format!("≈ ${:.2}", cost))
+            .unwrap_or_else(|| "no price".to_string());
+
+        lines.push(format!(
+            "{}: {} prompt + {} completion tokens, {} requests {}",
+            row.model,
+            tokens(row.prompt_tokens),
+            tokens(row.completion_tokens),
+            row.requests,
+            cost
+        ));
+    }
+
+    bot.send_message(chat_id, lines.join("\n")).await?;
+
+    Ok(())
+}
@@ -5,4 +5,5 @@ pub mod gpt;
 pub mod help;
 pub mod reset;
 pub mod util;
+pub mod voice;
 pub mod web;
@@ -0,0 +1,57 @@
+use std::sync::Arc;
+use teloxide::{prelude::*, sugar::request::RequestReplyExt, types::Me};
+use tracing::info;
+
+use crate::{
+    keyboard,
+    providers::{Provider, Transcriber},
+    types::{
+        auth::AuthState,
+        chat::ActiveReplies,
+        common::{BotDialogue, HandleResult},
+        databases::Database,
+    },
+    utils::files::download,
+};
+
+/// Transcribes a voice message and handles the text as if it had been typed, so it
+/// goes wherever the current dialogue state expects input.
+#[allow(clippy::too_many_arguments)]
+pub async fn transcribe(
+    transcriber: Transcriber,
+    provider: Provider,
+    replies: ActiveReplies,
+    auth_state: AuthState,
+    bot: Bot,
+    me: Me,
+    dialogue: BotDialogue,
+    msg: Message,
+    db: Arc<Database>,
+) -> HandleResult {
+    let Some(voice) = msg.voice() else {
+        return Ok(());
+    };
+
+    let audio = download(&bot, &voice.file.id).await?;
+    let text = transcriber.transcribe(audio, "voice.ogg").await?;
+    let text = text.trim().to_string();
+
+    info!("Voice message, user: {}, text: {}", msg.chat.id, text);
+
+    if text.is_empty() {
+        bot.send_message(msg.chat.id, "🎙 No speech recognized.")
+            .reply_to(msg.id)
+            .await?;
+
+        return Ok(());
+    }
+
+    bot.send_message(msg.chat.id, format!("🎙 {}", text))
+        .reply_to(msg.id)
+        .await?;
+
+    keyboard::core::handle_keyboard(
+        provider, replies, auth_state, bot, me, dialogue, msg, db, text,
+    )
+    .await
+}
@@ -0,0 +1,81 @@
+use crate::handlers::{auth, budgeting, gpt::usage};
+use crate::providers::Provider;
+use crate::types::{
+    common::{CachedInsights, InsightsCache},
+    databases::Database,
+};
+use crate::{config::Config, env::Env};
+use actix_web::web::Data;
+use actix_web::{web, Error as ActixError, HttpRequest, HttpResponse};
+use std::sync::Arc;
+use teloxide::types::ChatId;
+use tracing::warn;
+
+/// The report is only written again once the month's figures change, the AI usage
+/// counts against the same quota as the Telegram requests.
+pub async fn get(
+    req: HttpRequest,
+    jwt_secret: web::Data<String>,
+    _env: web::Data<Arc<Env>>,
+    config: web::Data<Arc<Config>>,
+    db: Data<Arc<Database>>,
+    provider: Data<Provider>,
+    cache: InsightsCache,
+) -> Result<HttpResponse, ActixError> {
+    let (user_id, _) = auth::jwt::authorize_request(req, jwt_secret, config.web.auth)?;
+
+    let parsed_user_id: i64 = user_id.trim().parse::<i64>().unwrap_or_default();
+
+    let Some(mut insights) = budgeting::insights::month_figures(&db, parsed_user_id).await else {
+        return Ok(HttpResponse::Ok().json(serde_json::json!({ "data": null })));
+    };
+
+    let figures = serde_json::to_string(&insights)?;
+
+    let cached = cache.lock().ok().and_then(|cache| {
+        cache
+            .get(&parsed_user_id)
+            .filter(|cached| cached.figures == figures)
+            .map(|cached| cached.report.clone())
+    });
+
+    if let Some(report) = cached {
+        insights.report = report;
+
+        return Ok(HttpResponse::Ok().json(serde_json::json!({ "data": insights })));
+    }
+
+    let within_quota = usage::within_quota(&db.usage(), ChatId(parsed_user_id))
+        .await
+        .map_err(|e| {
+            warn!("Failed to check the AI quota: {:?}", e);
+
+            actix_web::error::ErrorInternalServerError("Failed to generate insights")
+        })?;
+
+    if !within_quota {
+        return Err(actix_web::error::ErrorTooManyRequests(
+            "The AI quota is used up",
+        ));
+    }
+
+    insights.report = budgeting::insights::report(&provider, &db, parsed_user_id, &insights)
+        .await
+        .map_err(|e| {
+            warn!("Failed to generate insights: {:?}", e);
+
+            actix_web::error::ErrorInternalServerError("Failed to generate insights")
+        })?;
+
+    if let Ok(mut cache) = cache.lock() {
+        cache.insert(
+            parsed_user_id,
+            CachedInsights {
+                figures,
+                report: insights.report.clone(),
+            },
+        );
+    }
+
+    Ok(HttpResponse::Ok().json(serde_json::json!({ "data": insights })))
+}
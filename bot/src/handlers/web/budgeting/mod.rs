@@ -1,2 +1,3 @@
+pub mod insights;
 pub mod overview;
 pub mod transactions;
@@ -112,7 +112,7 @@ pub async fn get(
         monthly_spending_summaries,
     };
 
-    month_transactions.sort_by(|a, b| b.date.cmp(&a.date));
+    month_transactions.sort_by_key(|b| std::cmp::Reverse(b.date));
 
     let response = OverviewResponse {
         currency: "EUR".to_string(),
@@ -28,7 +28,7 @@ pub async fn get(
         .list_filtered(parsed_user_id, DateFilter::CurrentYear)
         .await;
 
-    all_year_txs.sort_by(|a, b| a.date.cmp(&b.date));
+    all_year_txs.sort_by_key(|a| a.date);
 
     let mut accumulated_balance = 0.0;
     let mut transactions: Vec<BudgetingTransaction> = vec![];
@@ -61,7 +61,7 @@ pub async fn create_categories_keyboard(
 
         let mut categories = categories_db.list(kind).await;
 
-        categories.sort_by(|a, b| a.id.cmp(&b.id));
+        categories.sort_by_key(|a| a.id);
 
         for category in categories {
             let id = category.id;
@@ -8,10 +8,12 @@ pub fn create_budgeting_menu_keyboard() -> ReplyMarkup {
             KeyboardButton::new(BudgetingMenuItems::AddIncome),
             KeyboardButton::new(BudgetingMenuItems::AddSpending),
         ],
+        vec![KeyboardButton::new(BudgetingMenuItems::QuickAdd)],
         vec![
             KeyboardButton::new(BudgetingMenuItems::Statistics),
             KeyboardButton::new(BudgetingMenuItems::Transactions),
         ],
+        vec![KeyboardButton::new(BudgetingMenuItems::Insights)],
         vec![
             KeyboardButton::new(BudgetingMenuItems::Settings),
             KeyboardButton::new(BudgetingMenuItems::Categories),
@@ -72,3 +72,11 @@ pub async fn create_transactions_suggestions_keyboard(
 
     InlineKeyboardMarkup::new(rows)
 }
+
+pub fn create_transaction_proposal_keyboard() -> InlineKeyboardMarkup {
+    InlineKeyboardMarkup::new(vec![vec![
+        InlineKeyboardButton::callback("✅ Confirm", "proposal:confirm"),
+        InlineKeyboardButton::callback("✏️ Edit", "proposal:edit"),
+        InlineKeyboardButton::callback("❌ Cancel", "proposal:cancel"),
+    ]])
+}
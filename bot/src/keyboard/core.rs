@@ -1,9 +1,8 @@
-use async_openai::{config::OpenAIConfig, Client};
 use std::{str::FromStr, sync::Arc};
 use teloxide::types::{MenuButton, WebAppInfo};
 use teloxide::{
     prelude::*,
-    types::{KeyboardButton, KeyboardMarkup, Me, ReplyMarkup},
+    types::{KeyboardButton, KeyboardMarkup, Me, MessageId, ReplyMarkup},
 };
 use tracing::info;
 use url::Url;
@@ -27,9 +26,12 @@ use crate::{
         },
         gpt::create_gpt_menu_keyboard,
     },
+    providers::Provider,
     types::{
+        chat::ActiveReplies,
         common::{
-            BotDialogue, ChatHistoryState, DateFilter, DialogueState, HandleResult, TransactionKind,
+            BotDialogue, DateFilter, DialogueState, HandleResult, TransactionKind,
+            TransactionProposal,
         },
         databases::Database,
         keyboard::{
@@ -104,8 +106,8 @@ pub async fn create_menu_button(bot: Bot, msg: Message) -> HandleResult {
 
 #[allow(clippy::too_many_arguments)]
 pub async fn handle_keyboard(
-    client: Client<OpenAIConfig>,
-    state: ChatHistoryState,
+    provider: Provider,
+    replies: ActiveReplies,
     _auth_state: AuthState,
     bot: Bot,
     _me: Me,
@@ -164,6 +166,38 @@ pub async fn handle_keyboard(
             OpenAIMenuItems::ExitChatMode => {
                 handlers::gpt::chat::exit(bot.clone(), dialogue.clone(), msg.clone()).await?;
             }
+            OpenAIMenuItems::NewConversation => {
+                bot.send_message(chat_id, "Name of the new conversation:")
+                    .await?;
+
+                dialogue
+                    .update(DialogueState::WaitingForConversationName)
+                    .await?;
+            }
+            OpenAIMenuItems::Conversations => {
+                handlers::gpt::conversations::list(bot.clone(), chat_id, &db.conversations())
+                    .await?;
+            }
+            OpenAIMenuItems::RenameConversation => {
+                bot.send_message(chat_id, "New name of the active conversation:")
+                    .await?;
+
+                dialogue
+                    .update(DialogueState::WaitingForConversationRename)
+                    .await?;
+            }
+            OpenAIMenuItems::SelectModel => {
+                handlers::gpt::models::menu(
+                    bot.clone(),
+                    provider.clone(),
+                    chat_id,
+                    &db.conversations(),
+                )
+                .await?;
+            }
+            OpenAIMenuItems::Personas => {
+                handlers::gpt::personas::list(bot.clone(), chat_id, &db.personas()).await?;
+            }
             OpenAIMenuItems::SetPrompt => {
                 bot.send_message(chat_id, "System prompt you want to set for the AI")
                     .await?;
@@ -171,10 +205,22 @@ pub async fn handle_keyboard(
                 dialogue.update(DialogueState::WaitingForNewPrompt).await?;
             }
             OpenAIMenuItems::ViewHistory => {
-                handlers::gpt::history::view(bot.clone(), state.clone(), msg.clone()).await?;
+                handlers::gpt::history::view(
+                    bot.clone(),
+                    &db.conversations(),
+                    &db.chat_messages(),
+                    msg.clone(),
+                )
+                .await?;
             }
             OpenAIMenuItems::ClearHistory => {
-                handlers::gpt::history::clear(bot.clone(), state.clone(), msg.clone()).await?;
+                handlers::gpt::history::clear(
+                    bot.clone(),
+                    &db.conversations(),
+                    &db.chat_messages(),
+                    msg.clone(),
+                )
+                .await?;
             }
             OpenAIMenuItems::Back => {
                 bot.send_message(chat_id, "Returning to Main Menu.")
@@ -206,6 +252,9 @@ pub async fn handle_keyboard(
                     .reply_markup(keyboard)
                     .await?;
             }
+            BudgetingMenuItems::Insights => {
+                handlers::budgeting::insights::send(bot, provider, &db, chat_id).await?;
+            }
             BudgetingMenuItems::AddIncome => {
                 handlers::budgeting::transactions::add_kind(
                     TransactionKind::Income,
@@ -226,6 +275,9 @@ pub async fn handle_keyboard(
                 )
                 .await?;
             }
+            BudgetingMenuItems::QuickAdd => {
+                handlers::budgeting::proposals::start(bot, chat_id, dialogue).await?;
+            }
             BudgetingMenuItems::Settings => {
                 handlers::budgeting::settings::open(bot, msg).await?;
             }
@@ -290,8 +342,9 @@ pub async fn handle_keyboard(
             handlers::gpt::chat::message(
                 text.clone(),
                 bot.clone(),
-                client.clone(),
-                state.clone(),
+                provider.clone(),
+                &db,
+                replies.clone(),
                 msg.clone(),
             )
             .await?;
@@ -299,15 +352,67 @@ pub async fn handle_keyboard(
             dialogue.update(DialogueState::Start).await?;
         }
         DialogueState::WaitingForNewPrompt => {
-            handlers::gpt::prompt::set(text.clone(), bot.clone(), state.clone(), msg.clone())
+            handlers::gpt::prompt::set(
+                text.clone(),
+                bot.clone(),
+                &db.conversations(),
+                &db.chat_messages(),
+                msg.clone(),
+            )
+            .await?;
+
+            dialogue.update(DialogueState::Start).await?;
+        }
+        DialogueState::WaitingForConversationName => {
+            handlers::gpt::conversations::create(
+                text.clone(),
+                bot.clone(),
+                chat_id,
+                &db.conversations(),
+            )
+            .await?;
+
+            dialogue.update(DialogueState::Start).await?;
+        }
+        DialogueState::WaitingForConversationRename => {
+            handlers::gpt::conversations::rename(
+                text.clone(),
+                bot.clone(),
+                chat_id,
+                &db.conversations(),
+            )
+            .await?;
+
+            dialogue.update(DialogueState::Start).await?;
+        }
+        DialogueState::WaitingForConversationImport => {
+            bot.send_message(chat_id, "Import cancelled, no file received.")
+                .await?;
+
+            dialogue.update(DialogueState::Start).await?;
+        }
+        DialogueState::WaitingForPersonaName => {
+            handlers::gpt::personas::set_name(text.clone(), bot.clone(), chat_id, dialogue.clone())
                 .await?;
+        }
+        DialogueState::WaitingForPersonaPrompt { id, name } => {
+            handlers::gpt::personas::save(
+                id,
+                name,
+                text.clone(),
+                bot.clone(),
+                chat_id,
+                &db.personas(),
+            )
+            .await?;
 
             dialogue.update(DialogueState::Start).await?;
         }
         DialogueState::InChatMode => {
             handlers::gpt::chat::message_in_chat_mode(
-                client.clone(),
-                state.clone(),
+                provider.clone(),
+                db.clone(),
+                replies.clone(),
                 bot.clone(),
                 msg.clone(),
                 text.clone(),
@@ -360,6 +465,23 @@ pub async fn handle_keyboard(
 
             dialogue.update(DialogueState::InBudgetingMenu).await?;
         }
+        DialogueState::WaitingForTransactionText { receipt_file_id }
+        | DialogueState::WaitingForTransactionConfirmation {
+            proposal: TransactionProposal {
+                receipt_file_id, ..
+            },
+        } => {
+            handlers::budgeting::proposals::propose(
+                text,
+                receipt_file_id,
+                bot,
+                chat_id,
+                dialogue,
+                provider.clone(),
+                &db,
+            )
+            .await?;
+        }
         DialogueState::InBudgetingMenu => {
             let keyboard = create_transactions_suggestions_keyboard(
                 bot.clone(),
@@ -400,8 +522,8 @@ pub async fn start(bot: Bot, msg: Message) -> HandleResult {
 
 #[allow(clippy::too_many_arguments)]
 pub async fn callback(
-    _client: Client<OpenAIConfig>,
-    _state: ChatHistoryState,
+    provider: Provider,
+    replies: ActiveReplies,
     bot: Bot,
     _me: Me,
     dialogue: BotDialogue,
@@ -515,6 +637,200 @@ pub async fn callback(
                     })
                     .await?;
             }
+            ["reply", "stop", id] => {
+                let message_id = MessageId(id.parse().unwrap_or_default());
+
+                handlers::gpt::chat::stop(
+                    bot.clone(),
+                    replies,
+                    ChatId::from(q.from.id),
+                    message_id,
+                )
+                .await?;
+            }
+            ["reply", "regenerate", id] => {
+                let message_id = MessageId(id.parse().unwrap_or_default());
+
+                bot.answer_callback_query(q.id.clone()).await?;
+
+                handlers::gpt::chat::regenerate(
+                    bot.clone(),
+                    provider,
+                    &db,
+                    replies,
+                    ChatId::from(q.from.id),
+                    message_id,
+                )
+                .await?;
+
+                return Ok(());
+            }
+            ["proposal", "confirm"] => {
+                handlers::budgeting::proposals::confirm(
+                    bot.clone(),
+                    ChatId::from(q.from.id),
+                    dialogue,
+                    &db,
+                )
+                .await?;
+            }
+            ["proposal", "edit"] => {
+                handlers::budgeting::proposals::edit(
+                    bot.clone(),
+                    ChatId::from(q.from.id),
+                    dialogue,
+                )
+                .await?;
+            }
+            ["proposal", "cancel"] => {
+                handlers::budgeting::proposals::cancel(
+                    bot.clone(),
+                    ChatId::from(q.from.id),
+                    dialogue,
+                )
+                .await?;
+            }
+            ["conversation", "switch", id] => {
+                handlers::gpt::conversations::switch(
+                    id.to_string(),
+                    bot.clone(),
+                    ChatId::from(q.from.id),
+                    &db.conversations(),
+                )
+                .await?;
+            }
+            ["persona", "new"] => {
+                handlers::gpt::personas::new(bot.clone(), ChatId::from(q.from.id), dialogue)
+                    .await?;
+            }
+            ["persona", "start", id] => {
+                handlers::gpt::personas::start(
+                    id.parse().unwrap_or_default(),
+                    bot.clone(),
+                    ChatId::from(q.from.id),
+                    &db.personas(),
+                    &db.conversations(),
+                    &db.chat_messages(),
+                )
+                .await?;
+            }
+            ["persona", "edit", id] => {
+                handlers::gpt::personas::edit(
+                    id.parse().unwrap_or_default(),
+                    bot.clone(),
+                    ChatId::from(q.from.id),
+                    dialogue,
+                    &db.personas(),
+                )
+                .await?;
+            }
+            ["persona", "delete", id] => {
+                handlers::gpt::personas::delete(
+                    id.parse().unwrap_or_default(),
+                    bot.clone(),
+                    ChatId::from(q.from.id),
+                    &db.personas(),
+                )
+                .await?;
+            }
+            ["document", "delete", id] => {
+                handlers::gpt::documents::delete(
+                    id.to_string(),
+                    bot.clone(),
+                    ChatId::from(q.from.id),
+                    &db.documents(),
+                )
+                .await?;
+            }
+            ["history", "page", conversation_id, page] => {
+                if let Some(message) = &q.message {
+                    handlers::gpt::history::page(
+                        conversation_id.parse().unwrap_or_default(),
+                        page.parse().unwrap_or_default(),
+                        bot.clone(),
+                        message.chat().id,
+                        message.id(),
+                        &db.conversations(),
+                        &db.chat_messages(),
+                    )
+                    .await?;
+                }
+            }
+            ["history", "expand", conversation_id, id] => {
+                handlers::gpt::history::expand(
+                    conversation_id.parse().unwrap_or_default(),
+                    id.parse().unwrap_or_default(),
+                    bot.clone(),
+                    ChatId::from(q.from.id),
+                    &db.conversations(),
+                    &db.chat_messages(),
+                )
+                .await?;
+            }
+            ["compare", "pick", id] => {
+                handlers::gpt::compare::pick(
+                    id.parse().unwrap_or_default(),
+                    bot.clone(),
+                    ChatId::from(q.from.id),
+                    &db,
+                    replies.clone(),
+                )
+                .await?;
+            }
+            ["job", "delete", id] => {
+                handlers::gpt::jobs::delete(
+                    id.to_string(),
+                    bot.clone(),
+                    ChatId::from(q.from.id),
+                    &db.jobs(),
+                )
+                .await?;
+            }
+            ["memory", "confirm", id] => {
+                handlers::gpt::memories::confirm(
+                    id.parse().unwrap_or_default(),
+                    bot.clone(),
+                    ChatId::from(q.from.id),
+                    &db.memories(),
+                )
+                .await?;
+            }
+            ["memory", "reject", id] => {
+                handlers::gpt::memories::reject(
+                    id.parse().unwrap_or_default(),
+                    bot.clone(),
+                    ChatId::from(q.from.id),
+                    &db.memories(),
+                )
+                .await?;
+            }
+            ["memory", "delete", id] => {
+                handlers::gpt::memories::forget(
+                    id.to_string(),
+                    bot.clone(),
+                    ChatId::from(q.from.id),
+                    &db.memories(),
+                )
+                .await?;
+            }
+            ["model", "select", index] => {
+                handlers::gpt::models::select(
+                    index.to_string(),
+                    bot.clone(),
+                    ChatId::from(q.from.id),
+                    &db.conversations(),
+                )
+                .await?;
+            }
+            ["conversation", "delete", id] => {
+                handlers::gpt::conversations::delete(
+                    id.to_string(),
+                    bot.clone(),
+                    ChatId::from(q.from.id),
+                    &db.conversations(),
+                )
+                .await?;
+            }
             _ => {
                 info!("Received unknown callback data: {}", data);
             }
@@ -1,8 +1,12 @@
-use teloxide::types::{KeyboardButton, KeyboardMarkup, ReplyMarkup};
+use teloxide::types::{
+    InlineKeyboardButton, InlineKeyboardMarkup, KeyboardButton, KeyboardMarkup, MessageId,
+    ReplyMarkup,
+};
 
 use crate::types::{
     common::{AppError, BotDialogue, DialogueState},
     keyboard::OpenAIMenuItems,
+    models::{ConversationRow, DocumentRow, JobRow, MemoryRow, PersonaRow},
 };
 
 pub async fn create_gpt_menu_keyboard(dialogue: BotDialogue) -> Result<ReplyMarkup, AppError> {
@@ -21,11 +25,22 @@ pub async fn create_gpt_menu_keyboard(dialogue: BotDialogue) -> Result<ReplyMark
 
     let keyboard_rows: Vec<Vec<KeyboardButton>> = vec![
         chat_controls,
+        vec![
+            KeyboardButton::new(OpenAIMenuItems::NewConversation),
+            KeyboardButton::new(OpenAIMenuItems::Conversations),
+        ],
         vec![
             KeyboardButton::new(OpenAIMenuItems::SetPrompt),
             KeyboardButton::new(OpenAIMenuItems::ViewHistory),
         ],
-        vec![KeyboardButton::new(OpenAIMenuItems::ClearHistory)],
+        vec![
+            KeyboardButton::new(OpenAIMenuItems::RenameConversation),
+            KeyboardButton::new(OpenAIMenuItems::ClearHistory),
+        ],
+        vec![
+            KeyboardButton::new(OpenAIMenuItems::SelectModel),
+            KeyboardButton::new(OpenAIMenuItems::Personas),
+        ],
         vec![KeyboardButton::new(OpenAIMenuItems::Back)],
     ];
 
@@ -40,3 +55,199 @@ pub async fn create_gpt_menu_keyboard(dialogue: BotDialogue) -> Result<ReplyMark
 
     Ok(custom_keyboard.into())
 }
+
+pub fn create_conversations_keyboard(
+    conversations: &[ConversationRow],
+    active_id: i64,
+) -> InlineKeyboardMarkup {
+    let rows: Vec<Vec<InlineKeyboardButton>> = conversations
+        .iter()
+        .map(|conversation| {
+            let label = if conversation.id == active_id {
+                format!("▶️ {}", conversation.name)
+            } else {
+                conversation.name.clone()
+            };
+
+            vec![
+                InlineKeyboardButton::callback(
+                    label,
+                    format!("conversation:switch:{}", conversation.id),
+                ),
+                InlineKeyboardButton::callback(
+                    "🗑",
+                    format!("conversation:delete:{}", conversation.id),
+                ),
+            ]
+        })
+        .collect();
+
+    InlineKeyboardMarkup::new(rows)
+}
+
+pub fn create_personas_keyboard(personas: &[PersonaRow]) -> InlineKeyboardMarkup {
+    let mut rows: Vec<Vec<InlineKeyboardButton>> = personas
+        .iter()
+        .map(|persona| {
+            vec![
+                InlineKeyboardButton::callback(
+                    format!("▶️ {}", persona.name),
+                    format!("persona:start:{}", persona.id),
+                ),
+                InlineKeyboardButton::callback("✏️", format!("persona:edit:{}", persona.id)),
+                InlineKeyboardButton::callback("🗑", format!("persona:delete:{}", persona.id)),
+            ]
+        })
+        .collect();
+
+    rows.push(vec![InlineKeyboardButton::callback(
+        "➕ New Persona",
+        "persona:new",
+    )]);
+
+    InlineKeyboardMarkup::new(rows)
+}
+
+pub fn create_documents_keyboard(documents: &[DocumentRow]) -> InlineKeyboardMarkup {
+    let rows: Vec<Vec<InlineKeyboardButton>> = documents
+        .iter()
+        .map(|document| {
+            vec![InlineKeyboardButton::callback(
+                format!("🗑 {}", document.name),
+                format!("document:delete:{}", document.id),
+            )]
+        })
+        .collect();
+
+    InlineKeyboardMarkup::new(rows)
+}
+
+/// Prev/Next navigation of a history page and expand buttons for its truncated turns,
+/// given as turn number and id.
+pub fn create_history_keyboard(
+    conversation_id: i64,
+    page: usize,
+    pages: usize,
+    truncated: &[(usize, i64)],
+) -> InlineKeyboardMarkup {
+    let mut navigation = Vec::new();
+
+    if page > 0 {
+        navigation.push(InlineKeyboardButton::callback(
+            "⬅️ Prev",
+            format!("history:page:{}:{}", conversation_id, page - 1),
+        ));
+    }
+
+    if page + 1 < pages {
+        navigation.push(InlineKeyboardButton::callback(
+            "Next ➡️",
+            format!("history:page:{}:{}", conversation_id, page + 1),
+        ));
+    }
+
+    let mut rows: Vec<Vec<InlineKeyboardButton>> = truncated
+        .chunks(4)
+        .map(|chunk| {
+            chunk
+                .iter()
+                .map(|(number, id)| {
+                    InlineKeyboardButton::callback(
+                        format!("🔎 #{}", number),
+                        format!("history:expand:{}:{}", conversation_id, id),
+                    )
+                })
+                .collect()
+        })
+        .collect();
+
+    if !navigation.is_empty() {
+        rows.push(navigation);
+    }
+
+    InlineKeyboardMarkup::new(rows)
+}
+
+pub fn create_jobs_keyboard(jobs: &[JobRow]) -> InlineKeyboardMarkup {
+    let rows: Vec<Vec<InlineKeyboardButton>> = jobs
+        .iter()
+        .map(|job| {
+            vec![InlineKeyboardButton::callback(
+                format!("🗑 {}", job.id),
+                format!("job:delete:{}", job.id),
+            )]
+        })
+        .collect();
+
+    InlineKeyboardMarkup::new(rows)
+}
+
+pub fn create_memories_keyboard(memories: &[MemoryRow]) -> InlineKeyboardMarkup {
+    let rows: Vec<Vec<InlineKeyboardButton>> = memories
+        .iter()
+        .map(|memory| {
+            vec![InlineKeyboardButton::callback(
+                format!("🗑 {}", memory.id),
+                format!("memory:delete:{}", memory.id),
+            )]
+        })
+        .collect();
+
+    InlineKeyboardMarkup::new(rows)
+}
+
+pub fn create_memory_proposal_keyboard(id: i64) -> InlineKeyboardMarkup {
+    InlineKeyboardMarkup::new(vec![vec![
+        InlineKeyboardButton::callback("✅ Remember", format!("memory:confirm:{}", id)),
+        InlineKeyboardButton::callback("❌ Skip", format!("memory:reject:{}", id)),
+    ]])
+}
+
+/// Models missing from the provider's `available` list are still shown, marked with a warning.
+pub fn create_models_keyboard(
+    models: &[String],
+    available: &[String],
+    active_model: &str,
+) -> InlineKeyboardMarkup {
+    let rows: Vec<Vec<InlineKeyboardButton>> = models
+        .iter()
+        .enumerate()
+        .map(|(index, model)| {
+            let label = if model == active_model {
+                format!("▶️ {}", model)
+            } else if !available.contains(model) {
+                format!("⚠️ {}", model)
+            } else {
+                model.clone()
+            };
+
+            vec![InlineKeyboardButton::callback(
+                label,
+                format!("model:select:{}", index),
+            )]
+        })
+        .collect();
+
+    InlineKeyboardMarkup::new(rows)
+}
+
+pub fn create_stop_keyboard(message_id: MessageId) -> InlineKeyboardMarkup {
+    InlineKeyboardMarkup::new(vec![vec![InlineKeyboardButton::callback(
+        "⏹ Stop",
+        format!("reply:stop:{}", message_id.0),
+    )]])
+}
+
+pub fn create_comparison_keyboard(answer_id: i64) -> InlineKeyboardMarkup {
+    InlineKeyboardMarkup::new(vec![vec![InlineKeyboardButton::callback(
+        "✅ Use this answer",
+        format!("compare:pick:{}", answer_id),
+    )]])
+}
+
+pub fn create_regenerate_keyboard(message_id: MessageId) -> InlineKeyboardMarkup {
+    InlineKeyboardMarkup::new(vec![vec![InlineKeyboardButton::callback(
+        "🔄 Regenerate",
+        format!("reply:regenerate:{}", message_id.0),
+    )]])
+}
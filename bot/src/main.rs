@@ -10,6 +10,8 @@ pub mod databases;
 pub mod env;
 pub mod handlers;
 pub mod keyboard;
+pub mod providers;
+pub mod scheduler;
 pub mod server;
 pub mod types;
 pub mod utils;
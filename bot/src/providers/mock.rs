@@ -0,0 +1,288 @@
+use async_openai::types::{
+    ChatCompletionRequestMessage, ChatCompletionRequestUserMessageContent,
+    ChatCompletionRequestUserMessageContentPart,
+};
+use async_trait::async_trait;
+use futures::{stream, StreamExt};
+use std::sync::atomic::{AtomicUsize, Ordering};
+
+use crate::{
+    providers::{
+        ChatProvider, Completion, CompletionChunk, CompletionRequest, CompletionStream, Embeddings,
+        TranscriptionProvider,
+    },
+    types::{chat::ToolCall, common::AppError},
+};
+
+const TOOL_PREFIX: &str = "tool:";
+
+const EMBEDDING_DIMENSIONS: usize = 64;
+
+const FNV_OFFSET: u64 = 0xcbf29ce484222325;
+
+const FNV_PRIME: u64 = 0x100000001b3;
+
+/// Offline provider replying with scripted responses in order, or echoing the
+/// latest user message when no responses are configured. A response written as
+/// `tool:<name> <json arguments>` is replayed as a tool call.
+pub struct MockProvider {
+    responses: Vec<String>,
+    models: Vec<String>,
+    cursor: AtomicUsize,
+}
+
+impl MockProvider {
+    pub fn new(responses: Vec<String>, models: Vec<String>) -> Self {
+        Self {
+            responses,
+            models,
+            cursor: AtomicUsize::new(0),
+        }
+    }
+
+    fn reply(&self, request: &CompletionRequest) -> String {
+        if self.responses.is_empty() {
+            return format!("Echo: {}", last_user_message(request).unwrap_or_default());
+        }
+
+        let index = self.cursor.fetch_add(1, Ordering::SeqCst) % self.responses.len();
+
+        self.responses[index].clone()
+    }
+}
+
+/// Offline transcriber returning the configured text, or a note with the audio size.
+pub struct MockTranscriber {
+    text: String,
+}
+
+impl MockTranscriber {
+    pub fn new(text: String) -> Self {
+        Self { text }
+    }
+}
+
+#[async_trait]
+impl TranscriptionProvider for MockTranscriber {
+    async fn transcribe(&self, audio: Vec<u8>, _filename: &str) -> Result<String, AppError> {
+        if self.text.is_empty() {
+            return Ok(format!("Voice message of {} bytes", audio.len()));
+        }
+
+        Ok(self.text.clone())
+    }
+}
+
+/// FNV-1a, unlike the std hasher its output is fixed across builds and Rust versions,
+/// so stored mock embeddings stay comparable.
+fn fnv1a(bytes: &[u8]) -> u64 {
+    bytes.iter().fold(FNV_OFFSET, |hash, byte| {
+        (hash ^ u64::from(*byte)).wrapping_mul(FNV_PRIME)
+    })
+}
+
+/// Bag of words vector with every lowercase word hashed into a bucket, texts sharing
+/// words end up similar, which is enough for offline retrieval.
+fn hashed_embedding(text: &str) -> Vec<f32> {
+    let mut vector = vec![0.0; EMBEDDING_DIMENSIONS];
+
+    for word in text
+        .split(|c: char| !c.is_alphanumeric())
+        .filter(|word| !word.is_empty())
+    {
+        let hash = fnv1a(word.to_lowercase().as_bytes());
+
+        vector[(hash % EMBEDDING_DIMENSIONS as u64) as usize] += 1.0;
+    }
+
+    vector
+}
+
+fn last_user_message(request: &CompletionRequest) -> Option<String> {
+    request
+        .messages
+        .iter()
+        .rev()
+        .find_map(|message| match message {
+            ChatCompletionRequestMessage::User(user) => match &user.content {
+                ChatCompletionRequestUserMessageContent::Text(text) => Some(text.clone()),
+                ChatCompletionRequestUserMessageContent::Array(parts) => Some(
+                    parts
+                        .iter()
+                        .map(|part| match part {
+                            ChatCompletionRequestUserMessageContentPart::Text(text) => {
+                                text.text.clone()
+                            }
+                            _ => "[image]".to_string(),
+                        })
+                        .collect::<Vec<String>>()
+                        .join(" "),
+                ),
+            },
+            _ => None,
+        })
+}
+
+#[async_trait]
+impl ChatProvider for MockProvider {
+    async fn complete(&self, request: CompletionRequest) -> Result<Completion, AppError> {
+        Ok(Completion {
+            content: self.reply(&request),
+            usage: None,
+        })
+    }
+
+    async fn stream(&self, request: CompletionRequest) -> Result<CompletionStream, AppError> {
+        let reply = self.reply(&request);
+
+        if let Some(call) = reply.strip_prefix(TOOL_PREFIX) {
+            let (name, arguments) = call.split_once(' ').unwrap_or((call, "{}"));
+            let tool_call = ToolCall {
+                id: format!("mock-call-{}", self.cursor.load(Ordering::SeqCst)),
+                name: name.to_string(),
+                arguments: arguments.to_string(),
+            };
+
+            return Ok(stream::iter(vec![Ok(CompletionChunk::ToolCalls(vec![tool_call]))]).boxed());
+        }
+
+        let chunks: Vec<Result<CompletionChunk, AppError>> = reply
+            .split_inclusive(' ')
+            .map(|chunk| Ok(CompletionChunk::Content(chunk.to_string())))
+            .collect();
+
+        Ok(stream::iter(chunks).boxed())
+    }
+
+    async fn models(&self) -> Result<Vec<String>, AppError> {
+        Ok(self.models.clone())
+    }
+
+    async fn embed(&self, _model: &str, inputs: Vec<String>) -> Result<Embeddings, AppError> {
+        Ok(Embeddings {
+            vectors: inputs.iter().map(|input| hashed_embedding(input)).collect(),
+            usage: None,
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::config::ModelParameters;
+    use async_openai::types::ChatCompletionRequestUserMessageArgs;
+
+    fn request(content: &str) -> CompletionRequest {
+        CompletionRequest {
+            model: "mock".into(),
+            messages: vec![ChatCompletionRequestUserMessageArgs::default()
+                .content(content)
+                .build()
+                .unwrap()
+                .into()],
+            parameters: ModelParameters::default(),
+            tools: Vec::new(),
+            schema: None,
+        }
+    }
+
+    #[tokio::test]
+    async fn echoes_last_user_message_without_script() {
+        let provider = MockProvider::new(vec![], vec![]);
+
+        let reply = provider.complete(request("hello")).await.unwrap();
+
+        assert_eq!(reply.content, "Echo: hello");
+    }
+
+    #[tokio::test]
+    async fn cycles_through_scripted_responses() {
+        let provider = MockProvider::new(vec!["first".into(), "second".into()], vec![]);
+
+        for (content, expected) in [("a", "first"), ("b", "second"), ("c", "first")] {
+            let reply = provider.complete(request(content)).await.unwrap();
+
+            assert_eq!(reply.content, expected);
+        }
+    }
+
+    #[tokio::test]
+    async fn streams_reply_in_chunks() {
+        let provider = MockProvider::new(vec!["one two three".into()], vec![]);
+
+        let chunks: Vec<CompletionChunk> = provider
+            .stream(request("a"))
+            .await
+            .unwrap()
+            .map(|chunk| chunk.unwrap())
+            .collect()
+            .await;
+
+        assert_eq!(
+            chunks,
+            vec![
+                CompletionChunk::Content("one ".into()),
+                CompletionChunk::Content("two ".into()),
+                CompletionChunk::Content("three".into()),
+            ]
+        );
+    }
+
+    #[tokio::test]
+    async fn replays_scripted_tool_calls() {
+        let provider = MockProvider::new(
+            vec![r#"tool:list_categories {"kind":"spending"}"#.into()],
+            vec![],
+        );
+
+        let chunks: Vec<CompletionChunk> = provider
+            .stream(request("a"))
+            .await
+            .unwrap()
+            .map(|chunk| chunk.unwrap())
+            .collect()
+            .await;
+
+        assert_eq!(
+            chunks,
+            vec![CompletionChunk::ToolCalls(vec![ToolCall {
+                id: "mock-call-1".into(),
+                name: "list_categories".into(),
+                arguments: r#"{"kind":"spending"}"#.into(),
+            }])]
+        );
+    }
+
+    #[test]
+    fn hashes_with_fnv1a() {
+        assert_eq!(fnv1a(b""), 0xcbf29ce484222325);
+        assert_eq!(fnv1a(b"a"), 0xaf63dc4c8601ec8c);
+        assert_eq!(fnv1a(b"foobar"), 0x85944171f73967e8);
+    }
+
+    #[tokio::test]
+    async fn embeds_case_insensitive_bags_of_words() {
+        let provider = MockProvider::new(vec![], vec![]);
+
+        let embeddings = provider
+            .embed("mock", vec!["Pets, pets".into(), "pets PETS".into()])
+            .await
+            .unwrap();
+
+        assert_eq!(embeddings.vectors[0], embeddings.vectors[1]);
+        assert_eq!(embeddings.vectors[0].iter().sum::<f32>(), 2.0);
+        assert_eq!(embeddings.usage, None);
+    }
+
+    #[tokio::test]
+    async fn transcribes_to_configured_text() {
+        let transcriber = MockTranscriber::new("spent 5 on coffee".into());
+
+        let text = transcriber
+            .transcribe(vec![0; 16], "voice.ogg")
+            .await
+            .unwrap();
+
+        assert_eq!(text, "spent 5 on coffee");
+    }
+}
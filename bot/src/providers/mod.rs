@@ -0,0 +1,163 @@
+pub mod mock;
+pub mod openai;
+
+use async_trait::async_trait;
+use futures::stream::BoxStream;
+use serde_json::Value;
+use std::sync::Arc;
+
+use crate::{
+    config::{ModelParameters, OpenAiConfig, ProviderKind, TranscriptionConfig},
+    env::ENV,
+    types::{
+        chat::ToolCall,
+        common::{AppError, ChatMessages},
+    },
+};
+
+pub type CompletionStream = BoxStream<'static, Result<CompletionChunk, AppError>>;
+pub type Provider = Arc<dyn ChatProvider>;
+pub type Transcriber = Arc<dyn TranscriptionProvider>;
+
+/// Function the model may call, `parameters` is a JSON schema of its arguments.
+#[derive(Debug, Clone)]
+pub struct ToolDefinition {
+    pub name: &'static str,
+    pub description: &'static str,
+    pub parameters: Value,
+}
+
+/// JSON schema the reply content must follow (structured output).
+#[derive(Debug, Clone)]
+pub struct ResponseSchema {
+    pub name: &'static str,
+    pub schema: Value,
+}
+
+#[derive(Debug, Clone)]
+pub struct CompletionRequest {
+    pub model: String,
+    pub messages: ChatMessages,
+    pub parameters: ModelParameters,
+    pub tools: Vec<ToolDefinition>,
+    pub schema: Option<ResponseSchema>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct TokenUsage {
+    pub prompt_tokens: u32,
+    pub completion_tokens: u32,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Embeddings {
+    pub vectors: Vec<Vec<f32>>,
+    /// Tokens reported by the backend, `None` when it does not report them.
+    pub usage: Option<TokenUsage>,
+}
+
+pub struct Completion {
+    pub content: String,
+    /// Token counts reported by the backend, `None` when it does not report them.
+    pub usage: Option<TokenUsage>,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum CompletionChunk {
+    /// Next piece of the answer content.
+    Content(String),
+    /// Complete tool calls, sent once at the end of the stream.
+    ToolCalls(Vec<ToolCall>),
+    /// Token counts of the whole request, sent at the end when the backend reports them.
+    Usage(TokenUsage),
+}
+
+/// Backend producing chat completions, handlers only talk to the model through it.
+#[async_trait]
+pub trait ChatProvider: Send + Sync {
+    /// Returns the whole completion at once.
+    async fn complete(&self, request: CompletionRequest) -> Result<Completion, AppError>;
+
+    /// Returns a stream of content deltas, followed by tool calls when the model requests any.
+    async fn stream(&self, request: CompletionRequest) -> Result<CompletionStream, AppError>;
+
+    /// Returns models known to the backend.
+    async fn models(&self) -> Result<Vec<String>, AppError>;
+
+    /// Returns one embedding vector per input, in input order.
+    async fn embed(&self, model: &str, inputs: Vec<String>) -> Result<Embeddings, AppError>;
+}
+
+/// Backend turning voice messages into text.
+#[async_trait]
+pub trait TranscriptionProvider: Send + Sync {
+    /// Returns the text spoken in the audio file, `filename` tells the audio format.
+    async fn transcribe(&self, audio: Vec<u8>, filename: &str) -> Result<String, AppError>;
+}
+
+pub fn create_transcriber(config: &TranscriptionConfig) -> Transcriber {
+    match config.provider {
+        ProviderKind::OpenAI => Arc::new(openai::OpenAITranscriber::new(
+            &ENV.open_api_key,
+            config.api_base.as_deref(),
+            &config.model,
+            config.language.clone(),
+        )),
+        ProviderKind::Mock => Arc::new(mock::MockTranscriber::new(config.mock_text.clone())),
+    }
+}
+
+pub fn create_provider(config: &OpenAiConfig) -> Provider {
+    match config.provider {
+        ProviderKind::OpenAI => Arc::new(openai::OpenAIProvider::new(&ENV.open_api_key, config)),
+        ProviderKind::Mock => Arc::new(mock::MockProvider::new(
+            config.mock_responses.clone(),
+            config.allowed_models(),
+        )),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::config::CONFIG;
+    use async_openai::types::ChatCompletionRequestUserMessageArgs;
+    use futures::StreamExt;
+
+    #[tokio::test]
+    async fn creates_configured_mock_provider() {
+        let mut config = CONFIG.open_ai.clone();
+
+        config.provider = ProviderKind::Mock;
+        config.mock_responses = vec!["Hello there".into()];
+
+        let provider = create_provider(&config);
+        let request = CompletionRequest {
+            model: config.model.clone(),
+            messages: vec![ChatCompletionRequestUserMessageArgs::default()
+                .content("Hi")
+                .build()
+                .unwrap()
+                .into()],
+            parameters: config.parameters(&config.model),
+            tools: Vec::new(),
+            schema: None,
+        };
+
+        let content: String = provider
+            .stream(request)
+            .await
+            .unwrap()
+            .filter_map(|chunk| async move {
+                match chunk.unwrap() {
+                    CompletionChunk::Content(content) => Some(content),
+                    _ => None,
+                }
+            })
+            .collect()
+            .await;
+
+        assert_eq!(content, "Hello there");
+        assert_eq!(provider.models().await.unwrap(), config.allowed_models());
+    }
+}
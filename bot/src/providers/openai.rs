@@ -0,0 +1,302 @@
+use async_openai::{
+    config::OpenAIConfig,
+    types::{
+        AudioInput, ChatCompletionResponseStream, ChatCompletionStreamOptions, ChatCompletionTool,
+        ChatCompletionToolType, CompletionUsage, CreateChatCompletionRequest,
+        CreateChatCompletionRequestArgs, CreateEmbeddingRequestArgs,
+        CreateTranscriptionRequestArgs, FunctionObject, ResponseFormat, ResponseFormatJsonSchema,
+    },
+    Client,
+};
+use async_trait::async_trait;
+use futures::{stream, StreamExt};
+
+use crate::{
+    config::OpenAiConfig,
+    providers::{
+        ChatProvider, Completion, CompletionChunk, CompletionRequest, CompletionStream, Embeddings,
+        TokenUsage, TranscriptionProvider,
+    },
+    types::{
+        chat::ToolCall,
+        common::{AppError, OpenAIClient},
+    },
+};
+
+pub struct OpenAIProvider {
+    client: OpenAIClient,
+    stream_usage: bool,
+    completion_tokens_models: Vec<String>,
+}
+
+pub struct OpenAITranscriber {
+    client: OpenAIClient,
+    model: String,
+    language: Option<String>,
+}
+
+fn client(api_key: &str, api_base: Option<&str>) -> OpenAIClient {
+    let mut config = OpenAIConfig::new().with_api_key(api_key);
+
+    if let Some(api_base) = api_base {
+        config = config.with_api_base(api_base);
+    }
+
+    Client::with_config(config)
+}
+
+/// Streamed tool calls arrive as fragments keyed by index, they are collected until the end.
+struct StreamState {
+    inner: ChatCompletionResponseStream,
+    tool_calls: Vec<ToolCall>,
+    usage: Option<TokenUsage>,
+    done: bool,
+}
+
+impl From<CompletionUsage> for TokenUsage {
+    fn from(usage: CompletionUsage) -> Self {
+        TokenUsage {
+            prompt_tokens: usage.prompt_tokens,
+            completion_tokens: usage.completion_tokens,
+        }
+    }
+}
+
+impl OpenAIProvider {
+    pub fn new(api_key: &str, config: &OpenAiConfig) -> Self {
+        Self {
+            client: client(api_key, config.api_base.as_deref()),
+            stream_usage: config.stream_usage,
+            completion_tokens_models: config.completion_tokens_models.clone(),
+        }
+    }
+
+    /// Servers without `stream_options` report no usage, the handlers estimate it then.
+    fn build(
+        &self,
+        request: CompletionRequest,
+        stream: bool,
+    ) -> Result<CreateChatCompletionRequest, AppError> {
+        let mut args = CreateChatCompletionRequestArgs::default();
+        let completion_tokens = self.completion_tokens_models.contains(&request.model);
+
+        args.model(request.model).messages(request.messages);
+
+        if stream && self.stream_usage {
+            args.stream_options(ChatCompletionStreamOptions {
+                include_usage: true,
+            });
+        }
+
+        if let Some(temperature) = request.parameters.temperature {
+            args.temperature(temperature);
+        }
+
+        match request.parameters.max_tokens {
+            Some(max_tokens) if completion_tokens => {
+                args.max_completion_tokens(max_tokens);
+            }
+            Some(max_tokens) => {
+                #[allow(deprecated)]
+                args.max_tokens(max_tokens);
+            }
+            None => {}
+        }
+
+        if let Some(top_p) = request.parameters.top_p {
+            args.top_p(top_p);
+        }
+
+        if !request.tools.is_empty() {
+            args.tools(
+                request
+                    .tools
+                    .into_iter()
+                    .map(|tool| ChatCompletionTool {
+                        r#type: ChatCompletionToolType::Function,
+                        function: FunctionObject {
+                            name: tool.name.to_string(),
+                            description: Some(tool.description.to_string()),
+                            parameters: Some(tool.parameters),
+                            strict: None,
+                        },
+                    })
+                    .collect::<Vec<_>>(),
+            );
+        }
+
+        if let Some(schema) = request.schema {
+            args.response_format(ResponseFormat::JsonSchema {
+                json_schema: ResponseFormatJsonSchema {
+                    description: None,
+                    name: schema.name.to_string(),
+                    schema: Some(schema.schema),
+                    strict: Some(true),
+                },
+            });
+        }
+
+        Ok(args.build()?)
+    }
+}
+
+async fn next_chunk(
+    mut state: StreamState,
+) -> Option<(Result<CompletionChunk, AppError>, StreamState)> {
+    while !state.done {
+        match state.inner.next().await {
+            Some(Ok(response)) => {
+                if let Some(usage) = response.usage {
+                    state.usage = Some(usage.into());
+                }
+
+                let Some(choice) = response.choices.into_iter().next() else {
+                    continue;
+                };
+
+                for call in choice.delta.tool_calls.unwrap_or_default() {
+                    let index = call.index as usize;
+
+                    if state.tool_calls.len() <= index {
+                        state.tool_calls.resize(index + 1, ToolCall::default());
+                    }
+
+                    let entry = &mut state.tool_calls[index];
+
+                    if let Some(id) = call.id {
+                        entry.id = id;
+                    }
+
+                    if let Some(function) = call.function {
+                        entry.name.push_str(&function.name.unwrap_or_default());
+                        entry
+                            .arguments
+                            .push_str(&function.arguments.unwrap_or_default());
+                    }
+                }
+
+                if let Some(content) = choice.delta.content.filter(|c| !c.is_empty()) {
+                    return Some((Ok(CompletionChunk::Content(content)), state));
+                }
+            }
+            Some(Err(e)) => {
+                state.done = true;
+                state.tool_calls.clear();
+                state.usage = None;
+
+                return Some((Err(e.into()), state));
+            }
+            None => state.done = true,
+        }
+    }
+
+    if !state.tool_calls.is_empty() {
+        let tool_calls = std::mem::take(&mut state.tool_calls);
+
+        return Some((Ok(CompletionChunk::ToolCalls(tool_calls)), state));
+    }
+
+    state
+        .usage
+        .take()
+        .map(|usage| (Ok(CompletionChunk::Usage(usage)), state))
+}
+
+#[async_trait]
+impl ChatProvider for OpenAIProvider {
+    async fn complete(&self, request: CompletionRequest) -> Result<Completion, AppError> {
+        let response = self
+            .client
+            .chat()
+            .create(self.build(request, false)?)
+            .await?;
+
+        Ok(Completion {
+            content: response
+                .choices
+                .first()
+                .and_then(|choice| choice.message.content.clone())
+                .unwrap_or_default(),
+            usage: response.usage.map(TokenUsage::from),
+        })
+    }
+
+    async fn stream(&self, request: CompletionRequest) -> Result<CompletionStream, AppError> {
+        let inner = self
+            .client
+            .chat()
+            .create_stream(self.build(request, true)?)
+            .await?;
+
+        let state = StreamState {
+            inner,
+            tool_calls: Vec::new(),
+            usage: None,
+            done: false,
+        };
+
+        Ok(stream::unfold(state, next_chunk).boxed())
+    }
+
+    async fn models(&self) -> Result<Vec<String>, AppError> {
+        let response = self.client.models().list().await?;
+
+        Ok(response.data.into_iter().map(|model| model.id).collect())
+    }
+
+    async fn embed(&self, model: &str, inputs: Vec<String>) -> Result<Embeddings, AppError> {
+        let request = CreateEmbeddingRequestArgs::default()
+            .model(model)
+            .input(inputs)
+            .build()?;
+
+        let response = self.client.embeddings().create(request).await?;
+        let mut data = response.data;
+
+        data.sort_by_key(|embedding| embedding.index);
+
+        Ok(Embeddings {
+            vectors: data
+                .into_iter()
+                .map(|embedding| embedding.embedding)
+                .collect(),
+            usage: Some(TokenUsage {
+                prompt_tokens: response.usage.prompt_tokens,
+                completion_tokens: 0,
+            }),
+        })
+    }
+}
+
+impl OpenAITranscriber {
+    pub fn new(
+        api_key: &str,
+        api_base: Option<&str>,
+        model: &str,
+        language: Option<String>,
+    ) -> Self {
+        Self {
+            client: client(api_key, api_base),
+            model: model.to_string(),
+            language,
+        }
+    }
+}
+
+#[async_trait]
+impl TranscriptionProvider for OpenAITranscriber {
+    async fn transcribe(&self, audio: Vec<u8>, filename: &str) -> Result<String, AppError> {
+        let mut args = CreateTranscriptionRequestArgs::default();
+
+        args.file(AudioInput::from_vec_u8(filename.to_string(), audio))
+            .model(self.model.clone());
+
+        if let Some(language) = &self.language {
+            args.language(language.clone());
+        }
+
+        let response = self.client.audio().transcribe(args.build()?).await?;
+
+        Ok(response.text)
+    }
+}
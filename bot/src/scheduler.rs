@@ -0,0 +1,71 @@
+use std::{sync::Arc, time::Duration};
+use teloxide::prelude::*;
+use tracing::{error, info, warn};
+
+use crate::{
+    config::CONFIG,
+    handlers::gpt::jobs,
+    providers::Provider,
+    types::{chat::ActiveReplies, common::HandleResult, databases::Database},
+    utils::schedule,
+};
+
+/// Runs due jobs every `scheduler.interval_seconds` until the process stops.
+pub async fn scheduler(bot: Bot, provider: Provider, db: Arc<Database>, replies: ActiveReplies) {
+    let mut interval = tokio::time::interval(Duration::from_secs(
+        CONFIG.scheduler.interval_seconds.max(1),
+    ));
+
+    info!("Scheduler started");
+
+    loop {
+        interval.tick().await;
+
+        if let Err(e) = run_due(&bot, &provider, &db, &replies).await {
+            error!("Scheduler failed: {:?}", e);
+        }
+    }
+}
+
+/// Moves due jobs to their next run before starting them, so a failing job is not retried
+/// on every tick. Runs missed while the bot was down are started once.
+async fn run_due(
+    bot: &Bot,
+    provider: &Provider,
+    db: &Arc<Database>,
+    replies: &ActiveReplies,
+) -> HandleResult {
+    let jobs_db = db.jobs();
+    let now = chrono::Utc::now().timestamp();
+
+    for job in jobs_db.due(now).await? {
+        let timezone = jobs::timezone_of(&jobs_db, job.chat_id).await?;
+        let next_run = schedule::parse(&job.schedule)
+            .ok()
+            .and_then(|schedule| schedule::next_run(&schedule, timezone, now));
+
+        match next_run {
+            Some(next_run) => jobs_db.set_next_run(job.id, next_run).await?,
+            None => {
+                warn!("Job {} never runs again, deleting it", job.id);
+
+                jobs_db.delete(job.chat_id, job.id).await?;
+            }
+        }
+
+        let bot = bot.clone();
+        let provider = provider.clone();
+        let db = db.clone();
+        let replies = replies.clone();
+
+        tokio::spawn(async move {
+            let id = job.id;
+
+            if let Err(e) = jobs::run(job, bot, provider, db, replies).await {
+                error!("Job {} failed: {:?}", id, e);
+            }
+        });
+    }
+
+    Ok(())
+}
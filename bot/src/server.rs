@@ -3,16 +3,18 @@ use crate::{
     config::CONFIG,
     env::ENV,
     handlers, keyboard,
+    providers::{create_provider, create_transcriber},
+    scheduler::scheduler,
     types::{
         auth::AuthState,
-        common::{ChatHistories, Commands, DialogueState},
+        chat::ActiveReplies,
+        common::{Commands, DialogueState, InsightsCache},
         databases::Database,
     },
 };
 use actix_cors::Cors;
 use actix_files::Files;
 use actix_web::{web, App, HttpServer};
-use async_openai::{config::OpenAIConfig, Client};
 use dotenv::dotenv;
 use std::{
     collections::HashMap,
@@ -20,6 +22,7 @@ use std::{
 };
 use teloxide::dispatching::dialogue::InMemStorage;
 use teloxide::prelude::*;
+use teloxide::types::UpdateKind;
 use tracing::{error, info, warn};
 use tracing_subscriber::{fmt, layer::SubscriberExt, util::SubscriberInitExt, EnvFilter};
 
@@ -38,11 +41,12 @@ pub async fn server() {
 
     info!("initializing..");
 
-    let open_ai_config = OpenAIConfig::new().with_api_key(&ENV.open_api_key);
     let bot = Bot::new(&ENV.token);
-    let client: Client<OpenAIConfig> = Client::with_config(open_ai_config);
-    let state = Arc::new(Mutex::new(ChatHistories::new()));
+    let provider = create_provider(&CONFIG.open_ai);
+    let transcriber = create_transcriber(&CONFIG.transcription);
+    let replies = ActiveReplies::default();
     let auth_state: AuthState = web::Data::new(Arc::new(Mutex::new(HashMap::new())));
+    let insights_cache: InsightsCache = web::Data::new(Arc::new(Mutex::new(HashMap::new())));
     let db = Arc::new(Database::new().await);
 
     let dialogue_storage = InMemStorage::<DialogueState>::new();
@@ -50,6 +54,7 @@ pub async fn server() {
     let is_dev = cfg!(debug_assertions);
     let web_auth_state = auth_state.clone();
     let web_db = db.clone();
+    let web_provider = provider.clone();
 
     let api_server = tokio::spawn(async move {
         HttpServer::new(move || {
@@ -70,10 +75,12 @@ pub async fn server() {
             App::new()
                 .wrap(cors)
                 .app_data(web_auth_state.clone())
+                .app_data(insights_cache.clone())
                 .app_data(web::Data::new(jwt_secret.clone()))
                 .app_data(web::Data::new(Arc::new(ENV.clone())))
                 .app_data(web::Data::new(Arc::new(CONFIG.clone())))
                 .app_data(web::Data::new(web_db.clone()))
+                .app_data(web::Data::new(web_provider.clone()))
                 .route("/api/user", web::get().to(handlers::web::user::get))
                 .route(
                     "/api/auth/login",
@@ -87,6 +94,10 @@ pub async fn server() {
                     "/api/budgeting/overview",
                     web::get().to(handlers::web::budgeting::overview::get),
                 )
+                .route(
+                    "/api/budgeting/insights",
+                    web::get().to(handlers::web::budgeting::insights::get),
+                )
                 .route(
                     "/api/budgeting/transactions",
                     web::get().to(handlers::web::budgeting::transactions::get),
@@ -144,12 +155,58 @@ pub async fn server() {
         .filter(|msg: Message| msg.text().is_some())
         .map(|msg: Message| msg.text().unwrap().to_string());
 
+    let photo_handler = Update::filter_message()
+        .filter(|msg: Message| msg.photo().is_some())
+        .branch(
+            dptree::filter(|state: DialogueState| {
+                matches!(
+                    state,
+                    DialogueState::InBudgetingMenu
+                        | DialogueState::InTransactionsMode
+                        | DialogueState::WaitingForTransactionText { .. }
+                        | DialogueState::WaitingForTransactionConfirmation { .. }
+                )
+            })
+            .endpoint(handlers::budgeting::proposals::receipt),
+        )
+        .endpoint(handlers::gpt::chat::photo);
+
+    let voice_handler = Update::filter_message()
+        .filter(|msg: Message| msg.voice().is_some())
+        .endpoint(handlers::voice::transcribe);
+
+    let document_handler = Update::filter_message()
+        .filter(|msg: Message| msg.document().is_some())
+        .branch(
+            dptree::case![DialogueState::WaitingForConversationImport]
+                .endpoint(handlers::gpt::transfer::import),
+        )
+        .endpoint(handlers::gpt::documents::upload);
+
     let commands_handler = is_authorized
         .clone()
         .filter_command::<Commands>()
         .endpoint(commands);
 
+    let group_handler = Update::filter_message()
+        .filter(|msg: Message| !msg.chat.is_private())
+        .branch(
+            dptree::filter(|msg: Message| handlers::gpt::groups::is_allowed(msg.chat.id))
+                .endpoint(handlers::gpt::groups::message),
+        )
+        .endpoint(handlers::gpt::groups::ignore);
+
+    let group_callback_handler = Update::filter_callback_query()
+        .filter(|q: CallbackQuery| {
+            q.message
+                .as_ref()
+                .is_some_and(|message| !message.chat().is_private())
+        })
+        .endpoint(handlers::gpt::groups::callback);
+
     let handler = dptree::entry()
+        .branch(group_callback_handler)
+        .branch(group_handler)
         .branch(
             Update::filter_callback_query()
                 .enter_dialogue::<CallbackQuery, InMemStorage<DialogueState>, DialogueState>()
@@ -180,6 +237,9 @@ pub async fn server() {
                             ),
                     ),
                 )
+                .branch(is_authorized.clone().branch(photo_handler))
+                .branch(is_authorized.clone().branch(voice_handler))
+                .branch(is_authorized.clone().branch(document_handler))
                 .branch(is_authorized.clone().branch(commands_handler.clone()))
                 .branch(
                     is_authorized
@@ -190,15 +250,28 @@ pub async fn server() {
 
     let bot_auth_state = auth_state.clone();
 
+    tokio::spawn(scheduler(
+        bot.clone(),
+        provider.clone(),
+        db.clone(),
+        replies.clone(),
+    ));
+
     let mut bot_dispatcher = Dispatcher::builder(bot.clone(), handler)
         .dependencies(dptree::deps![
-            client,
-            state,
+            provider,
+            transcriber,
+            replies,
             bot_auth_state,
             dialogue_storage,
             db.clone(),
             bot.clone().get_me().await.unwrap()
         ])
+        // Callback queries skip the per-chat queue, so Stop reaches a reply that is still streaming.
+        .distribution_function(|upd| match upd.kind {
+            UpdateKind::CallbackQuery(_) => None,
+            _ => upd.chat().map(|chat| chat.id),
+        })
         .default_handler(|upd| async move {
             warn!("Unhandled update: {:?}", upd);
         })
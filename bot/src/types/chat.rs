@@ -0,0 +1,696 @@
+use async_openai::types::{
+    ChatCompletionMessageToolCall, ChatCompletionRequestAssistantMessageArgs,
+    ChatCompletionRequestMessage, ChatCompletionRequestMessageContentPartImageArgs,
+    ChatCompletionRequestMessageContentPartTextArgs, ChatCompletionRequestSystemMessageArgs,
+    ChatCompletionRequestToolMessageArgs, ChatCompletionRequestUserMessageArgs,
+    ChatCompletionRequestUserMessageContentPart, ChatCompletionToolType, FunctionCall,
+    ImageUrlArgs,
+};
+use chrono::NaiveDateTime;
+use serde::{Deserialize, Serialize};
+use std::{
+    collections::{HashMap, VecDeque},
+    future::Future,
+    pin::Pin,
+    str::FromStr,
+    sync::{
+        atomic::{AtomicBool, AtomicUsize, Ordering},
+        Arc, Mutex,
+    },
+    time::{Duration, Instant},
+};
+use strum::{AsRefStr, EnumString, IntoStaticStr};
+use tokio::sync::mpsc::{unbounded_channel, UnboundedSender};
+
+use crate::{
+    types::{
+        common::{AppError, ChatMessages},
+        models::ChatMessageRow,
+    },
+    utils::tokens::count_tokens,
+};
+
+const SUMMARY_KIND: &str = "summary";
+
+/// Window of the per-chat request rate limit.
+const RATE_WINDOW: Duration = Duration::from_secs(60);
+
+#[derive(Debug, Clone, Copy, PartialEq, EnumString, IntoStaticStr, AsRefStr)]
+pub enum ChatRole {
+    #[strum(serialize = "system")]
+    System,
+    #[strum(serialize = "user")]
+    User,
+    #[strum(serialize = "assistant")]
+    Assistant,
+    #[strum(serialize = "tool")]
+    Tool,
+}
+
+/// Function call requested by the model, `arguments` is a JSON object string.
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct ToolCall {
+    pub id: String,
+    pub name: String,
+    pub arguments: String,
+}
+
+impl From<&ToolCall> for ChatCompletionMessageToolCall {
+    fn from(call: &ToolCall) -> Self {
+        ChatCompletionMessageToolCall {
+            id: call.id.clone(),
+            r#type: ChatCompletionToolType::Function,
+            function: FunctionCall {
+                name: call.name.clone(),
+                arguments: call.arguments.clone(),
+            },
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct ChatTurn {
+    pub id: i64,
+    pub role: ChatRole,
+    pub content: String,
+    pub tokens: usize,
+    pub tool_calls: Vec<ToolCall>,
+    pub tool_call_id: Option<String>,
+    /// Data URL of an image attached to a user turn.
+    pub image: Option<String>,
+    pub created_at: NaiveDateTime,
+}
+
+impl ChatTurn {
+    pub fn to_request_message(&self) -> Result<ChatCompletionRequestMessage, AppError> {
+        let message = match self.role {
+            ChatRole::System => ChatCompletionRequestSystemMessageArgs::default()
+                .content(self.content.clone())
+                .build()?
+                .into(),
+            ChatRole::User if self.image.is_some() => {
+                let mut parts: Vec<ChatCompletionRequestUserMessageContentPart> = Vec::new();
+
+                if !self.content.is_empty() {
+                    parts.push(
+                        ChatCompletionRequestMessageContentPartTextArgs::default()
+                            .text(self.content.clone())
+                            .build()?
+                            .into(),
+                    );
+                }
+
+                parts.push(
+                    ChatCompletionRequestMessageContentPartImageArgs::default()
+                        .image_url(
+                            ImageUrlArgs::default()
+                                .url(self.image.clone().unwrap_or_default())
+                                .build()?,
+                        )
+                        .build()?
+                        .into(),
+                );
+
+                ChatCompletionRequestUserMessageArgs::default()
+                    .content(parts)
+                    .build()?
+                    .into()
+            }
+            ChatRole::User => ChatCompletionRequestUserMessageArgs::default()
+                .content(self.content.clone())
+                .build()?
+                .into(),
+            ChatRole::Assistant if !self.tool_calls.is_empty() => {
+                let mut args = ChatCompletionRequestAssistantMessageArgs::default();
+
+                if !self.content.is_empty() {
+                    args.content(self.content.clone());
+                }
+
+                args.tool_calls(
+                    self.tool_calls
+                        .iter()
+                        .map(ChatCompletionMessageToolCall::from)
+                        .collect::<Vec<_>>(),
+                )
+                .build()?
+                .into()
+            }
+            ChatRole::Assistant => ChatCompletionRequestAssistantMessageArgs::default()
+                .content(self.content.clone())
+                .build()?
+                .into(),
+            ChatRole::Tool => ChatCompletionRequestToolMessageArgs::default()
+                .content(self.content.clone())
+                .tool_call_id(self.tool_call_id.clone().unwrap_or_default())
+                .build()?
+                .into(),
+        };
+
+        Ok(message)
+    }
+}
+
+impl TryFrom<ChatMessageRow> for ChatTurn {
+    type Error = AppError;
+
+    fn try_from(row: ChatMessageRow) -> Result<Self, Self::Error> {
+        let role = ChatRole::from_str(&row.role)
+            .map_err(|_| AppError::InternalError(format!("Unknown chat role: {}", row.role)))?;
+
+        let tool_calls: Vec<ToolCall> = match &row.tool_calls {
+            Some(json) => serde_json::from_str(json)
+                .map_err(|e| AppError::InternalError(format!("Invalid tool calls: {}", e)))?,
+            None => Vec::new(),
+        };
+
+        let tokens = if row.tokens > 0 {
+            row.tokens as usize
+        } else {
+            count_tokens("", &row.content)
+        };
+
+        Ok(ChatTurn {
+            id: row.id,
+            role,
+            content: row.content,
+            tokens,
+            tool_calls,
+            tool_call_id: row.tool_call_id,
+            image: row.image,
+            created_at: row.created_at,
+        })
+    }
+}
+
+/// Version of the conversation export format, bumped on incompatible changes.
+pub const EXPORT_VERSION: u32 = 1;
+
+/// Conversation written by `/export json` and read back by `/import`.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ConversationExport {
+    pub version: u32,
+    pub name: String,
+    pub model: Option<String>,
+    pub exported_at: NaiveDateTime,
+    pub messages: Vec<ExportedMessage>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ExportedMessage {
+    pub role: String,
+    #[serde(default = "default_kind")]
+    pub kind: String,
+    pub content: String,
+    #[serde(default)]
+    pub tokens: i64,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub tool_calls: Vec<ToolCall>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub tool_call_id: Option<String>,
+    /// Data URL of an image attached to a user turn.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub image: Option<String>,
+    pub created_at: NaiveDateTime,
+}
+
+fn default_kind() -> String {
+    "message".to_string()
+}
+
+impl TryFrom<ChatMessageRow> for ExportedMessage {
+    type Error = AppError;
+
+    fn try_from(row: ChatMessageRow) -> Result<Self, Self::Error> {
+        let tool_calls: Vec<ToolCall> = match &row.tool_calls {
+            Some(json) => serde_json::from_str(json)
+                .map_err(|e| AppError::InternalError(format!("Invalid tool calls: {}", e)))?,
+            None => Vec::new(),
+        };
+
+        Ok(ExportedMessage {
+            role: row.role,
+            kind: row.kind,
+            content: row.content,
+            tokens: row.tokens,
+            tool_calls,
+            tool_call_id: row.tool_call_id,
+            image: row.image,
+            created_at: row.created_at,
+        })
+    }
+}
+
+/// Chat history split into the pinned system prompt, an optional summary of
+/// collapsed older turns and the remaining user/assistant turns.
+#[derive(Debug, Clone, Default)]
+pub struct Conversation {
+    pub system: Option<ChatTurn>,
+    pub summary: Option<ChatTurn>,
+    pub turns: Vec<ChatTurn>,
+}
+
+impl Conversation {
+    pub fn from_rows(rows: Vec<ChatMessageRow>) -> Result<Self, AppError> {
+        let mut conversation = Conversation::default();
+
+        for row in rows {
+            let is_summary = row.kind == SUMMARY_KIND;
+            let turn = ChatTurn::try_from(row)?;
+
+            match turn.role {
+                ChatRole::System if is_summary => conversation.summary = Some(turn),
+                ChatRole::System => conversation.system = Some(turn),
+                _ => conversation.turns.push(turn),
+            }
+        }
+
+        Ok(conversation)
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.system.is_none() && self.summary.is_none() && self.turns.is_empty()
+    }
+
+    pub fn total_tokens(&self) -> usize {
+        self.system
+            .iter()
+            .chain(self.summary.iter())
+            .chain(self.turns.iter())
+            .map(|turn| turn.tokens)
+            .sum()
+    }
+
+    /// Drops the oldest turns until the conversation fits the budget, always keeping
+    /// the system prompt and the latest turn.
+    pub fn truncated(mut self, budget: usize) -> Self {
+        while self.total_tokens() > budget && self.turns.len() > 1 {
+            self.turns.remove(0);
+            self.drop_orphan_tool_results();
+        }
+
+        if self.total_tokens() > budget {
+            self.summary = None;
+        }
+
+        self
+    }
+
+    /// Counts the tokens of every turn again with the tokenizer of `model`.
+    pub fn recounted(mut self, model: &str) -> Self {
+        for turn in self
+            .system
+            .iter_mut()
+            .chain(self.summary.iter_mut())
+            .chain(self.turns.iter_mut())
+        {
+            turn.tokens = count_tokens(model, &turn.content);
+        }
+
+        self
+    }
+
+    /// Replaces images with a placeholder, for models without image input.
+    pub fn without_images(mut self) -> Self {
+        for turn in self.turns.iter_mut().filter(|turn| turn.image.is_some()) {
+            turn.image = None;
+            turn.content = format!("[image] {}", turn.content).trim_end().to_string();
+        }
+
+        self
+    }
+
+    /// Tool results are only valid right after the assistant turn requesting them.
+    fn drop_orphan_tool_results(&mut self) {
+        while self.turns.len() > 1 && self.turns[0].role == ChatRole::Tool {
+            self.turns.remove(0);
+        }
+    }
+
+    /// Splits turns into the oldest ones to be collapsed into a summary and the latest
+    /// ones fitting into `target` tokens together with the system prompt.
+    pub fn split_for_summary(&self, target: usize) -> (Vec<ChatTurn>, Vec<ChatTurn>) {
+        let mut available = target.saturating_sub(self.system.as_ref().map_or(0, |s| s.tokens));
+        let mut keep_from = self.turns.len();
+
+        while keep_from > 0 {
+            let tokens = self.turns[keep_from - 1].tokens;
+
+            if tokens > available && keep_from < self.turns.len() {
+                break;
+            }
+
+            available = available.saturating_sub(tokens);
+            keep_from -= 1;
+        }
+
+        while keep_from < self.turns.len() && self.turns[keep_from].role == ChatRole::Tool {
+            keep_from += 1;
+        }
+
+        let (old, recent) = self.turns.split_at(keep_from);
+
+        (old.to_vec(), recent.to_vec())
+    }
+
+    /// Builds request messages with the system prompt and summary first, followed by
+    /// at most `limit` latest turns.
+    pub fn to_request_messages(&self, limit: Option<usize>) -> Result<ChatMessages, AppError> {
+        let mut skip = limit
+            .map(|limit| self.turns.len().saturating_sub(limit))
+            .unwrap_or(0);
+
+        while skip > 0 && skip < self.turns.len() && self.turns[skip].role == ChatRole::Tool {
+            skip += 1;
+        }
+
+        let mut messages = ChatMessages::new();
+
+        if let Some(system) = &self.system {
+            messages.push(system.to_request_message()?);
+        }
+
+        if let Some(summary) = &self.summary {
+            messages.push(
+                ChatCompletionRequestSystemMessageArgs::default()
+                    .content(format!(
+                        "Summary of the earlier conversation:\n{}",
+                        summary.content
+                    ))
+                    .build()?
+                    .into(),
+            );
+        }
+
+        for turn in self.turns.iter().skip(skip) {
+            messages.push(turn.to_request_message()?);
+        }
+
+        Ok(messages)
+    }
+}
+
+type StopFlags = HashMap<(i64, i32), Arc<AtomicBool>>;
+
+type QueuedReply = Pin<Box<dyn Future<Output = ()> + Send>>;
+
+type ReplyQueues = Arc<Mutex<HashMap<i64, ReplyQueue>>>;
+
+/// Worker answering the queued requests of one conversation in order, it exits and
+/// leaves `queues` once the queue drains.
+struct ReplyQueue {
+    sender: UnboundedSender<QueuedReply>,
+    pending: Arc<AtomicUsize>,
+}
+
+impl ReplyQueue {
+    fn spawn(conversation_id: i64, queues: ReplyQueues) -> Self {
+        let (sender, mut receiver) = unbounded_channel::<QueuedReply>();
+        let pending = Arc::new(AtomicUsize::new(0));
+        let remaining = pending.clone();
+
+        tokio::spawn(async move {
+            while let Some(reply) = receiver.recv().await {
+                reply.await;
+
+                // Checked under the lock, so a request is either counted here or gets a new worker.
+                let mut queues = queues.lock().unwrap();
+
+                if remaining.load(Ordering::SeqCst) == 0 {
+                    if queues
+                        .get(&conversation_id)
+                        .is_some_and(|queue| Arc::ptr_eq(&queue.pending, &remaining))
+                    {
+                        queues.remove(&conversation_id);
+                    }
+
+                    break;
+                }
+            }
+        });
+
+        Self { sender, pending }
+    }
+}
+
+/// Replies in flight: stop flags of streaming replies keyed by chat and placeholder
+/// message id, per-conversation queues and recent request times per chat.
+#[derive(Clone, Default)]
+pub struct ActiveReplies {
+    flags: Arc<Mutex<StopFlags>>,
+    queues: ReplyQueues,
+    requests: Arc<Mutex<HashMap<i64, VecDeque<Instant>>>>,
+}
+
+impl ActiveReplies {
+    pub fn register(&self, chat_id: i64, message_id: i32) -> Arc<AtomicBool> {
+        let flag = Arc::new(AtomicBool::new(false));
+
+        self.flags
+            .lock()
+            .unwrap()
+            .insert((chat_id, message_id), flag.clone());
+
+        flag
+    }
+
+    /// Flags the reply to stop, returns false when it is not streaming anymore.
+    pub fn stop(&self, chat_id: i64, message_id: i32) -> bool {
+        match self.flags.lock().unwrap().get(&(chat_id, message_id)) {
+            Some(flag) => {
+                flag.store(true, Ordering::SeqCst);
+                true
+            }
+            None => false,
+        }
+    }
+
+    pub fn finish(&self, chat_id: i64, message_id: i32) {
+        self.flags.lock().unwrap().remove(&(chat_id, message_id));
+    }
+
+    /// Requests of the conversation queued or running.
+    pub fn queued(&self, conversation_id: i64) -> usize {
+        self.queues
+            .lock()
+            .unwrap()
+            .get(&conversation_id)
+            .map_or(0, |queue| queue.pending.load(Ordering::SeqCst))
+    }
+
+    /// Runs `reply` after the replies queued before it for the same conversation.
+    pub fn enqueue(&self, conversation_id: i64, reply: impl Future<Output = ()> + Send + 'static) {
+        let mut queues = self.queues.lock().unwrap();
+        let queue = queues
+            .entry(conversation_id)
+            .or_insert_with(|| ReplyQueue::spawn(conversation_id, self.queues.clone()));
+
+        // A panicking reply ends the worker, the next request starts a new one.
+        if queue.sender.is_closed() {
+            *queue = ReplyQueue::spawn(conversation_id, self.queues.clone());
+        }
+
+        let pending = queue.pending.clone();
+
+        pending.fetch_add(1, Ordering::SeqCst);
+
+        let _ = queue.sender.send(Box::pin(async move {
+            reply.await;
+            pending.fetch_sub(1, Ordering::SeqCst);
+        }));
+    }
+
+    /// Counts a request of the chat against `limit` requests per minute, returns how long
+    /// to wait instead when the limit is reached.
+    pub fn throttle(&self, chat_id: i64, limit: usize) -> Option<Duration> {
+        let now = Instant::now();
+        let mut requests = self.requests.lock().unwrap();
+
+        // Chats without requests in the window are dropped, not kept with an empty deque.
+        requests.retain(|_, recent| {
+            while recent
+                .front()
+                .is_some_and(|at| now.duration_since(*at) >= RATE_WINDOW)
+            {
+                recent.pop_front();
+            }
+
+            !recent.is_empty()
+        });
+
+        let recent = requests.entry(chat_id).or_default();
+
+        if recent.len() >= limit {
+            return recent
+                .front()
+                .map(|oldest| RATE_WINDOW.saturating_sub(now.duration_since(*oldest)));
+        }
+
+        recent.push_back(now);
+
+        None
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn turn(id: i64, role: ChatRole, tokens: usize) -> ChatTurn {
+        ChatTurn {
+            id,
+            role,
+            content: format!("turn {}", id),
+            tokens,
+            tool_calls: Vec::new(),
+            tool_call_id: None,
+            image: None,
+            created_at: chrono::Utc::now().naive_utc(),
+        }
+    }
+
+    fn conversation(turns: &[usize]) -> Conversation {
+        Conversation {
+            system: Some(turn(0, ChatRole::System, 10)),
+            summary: None,
+            turns: turns
+                .iter()
+                .enumerate()
+                .map(|(index, &tokens)| {
+                    let role = if index % 2 == 0 {
+                        ChatRole::User
+                    } else {
+                        ChatRole::Assistant
+                    };
+
+                    turn(index as i64 + 1, role, tokens)
+                })
+                .collect(),
+        }
+    }
+
+    #[test]
+    fn keeps_conversation_at_the_budget() {
+        let conversation = conversation(&[30, 30, 30]);
+
+        assert_eq!(conversation.clone().truncated(100).turns.len(), 3);
+        assert_eq!(conversation.truncated(99).turns.len(), 2);
+    }
+
+    #[test]
+    fn truncation_keeps_system_prompt_and_latest_turn() {
+        let truncated = conversation(&[30, 30, 500]).truncated(100);
+
+        assert_eq!(truncated.system.map(|system| system.id), Some(0));
+        assert_eq!(
+            truncated
+                .turns
+                .iter()
+                .map(|turn| turn.id)
+                .collect::<Vec<_>>(),
+            vec![3]
+        );
+    }
+
+    #[test]
+    fn splits_oldest_turns_for_summary() {
+        let (collapsed, recent) = conversation(&[40, 40, 40, 40]).split_for_summary(90);
+
+        assert_eq!(
+            collapsed.iter().map(|turn| turn.id).collect::<Vec<_>>(),
+            vec![1, 2]
+        );
+        assert_eq!(
+            recent.iter().map(|turn| turn.id).collect::<Vec<_>>(),
+            vec![3, 4]
+        );
+    }
+
+    #[test]
+    fn recounts_every_turn_for_the_model() {
+        let recounted = conversation(&[500, 500]).recounted("gpt-4o");
+
+        assert_eq!(
+            recounted.total_tokens(),
+            3 * count_tokens("gpt-4o", "turn 1")
+        );
+    }
+
+    #[test]
+    fn throttles_requests_over_the_limit() {
+        let replies = ActiveReplies::default();
+
+        assert_eq!(replies.throttle(1, 2), None);
+        assert_eq!(replies.throttle(1, 2), None);
+        assert!(replies
+            .throttle(1, 2)
+            .is_some_and(|wait| wait <= RATE_WINDOW));
+        assert_eq!(replies.throttle(2, 2), None);
+        assert_eq!(replies.requests.lock().unwrap().len(), 2);
+    }
+
+    #[test]
+    fn forgets_chats_without_recent_requests() {
+        let replies = ActiveReplies::default();
+        let expired = Instant::now() - RATE_WINDOW;
+
+        replies
+            .requests
+            .lock()
+            .unwrap()
+            .insert(1, VecDeque::from([expired]));
+
+        assert_eq!(replies.throttle(2, 2), None);
+        assert_eq!(
+            replies.requests.lock().unwrap().keys().collect::<Vec<_>>(),
+            vec![&2]
+        );
+    }
+
+    #[tokio::test]
+    async fn answers_queued_replies_in_order() {
+        let replies = ActiveReplies::default();
+        let order = Arc::new(Mutex::new(Vec::new()));
+        let (done, finished) = tokio::sync::oneshot::channel();
+
+        for index in 0..3 {
+            let order = order.clone();
+
+            replies.enqueue(7, async move {
+                tokio::time::sleep(Duration::from_millis(10 * (3 - index))).await;
+                order.lock().unwrap().push(index);
+            });
+        }
+
+        replies.enqueue(7, async move {
+            let _ = done.send(());
+        });
+
+        finished.await.unwrap();
+
+        assert_eq!(*order.lock().unwrap(), vec![0, 1, 2]);
+    }
+
+    #[tokio::test]
+    async fn removes_drained_queues() {
+        let replies = ActiveReplies::default();
+        let (done, finished) = tokio::sync::oneshot::channel();
+
+        replies.enqueue(7, async move {
+            let _ = done.send(());
+        });
+
+        finished.await.unwrap();
+
+        for _ in 0..100 {
+            if replies.queues.lock().unwrap().is_empty() {
+                break;
+            }
+
+            tokio::time::sleep(Duration::from_millis(10)).await;
+        }
+
+        assert!(replies.queues.lock().unwrap().is_empty());
+        assert_eq!(replies.queued(7), 0);
+    }
+}
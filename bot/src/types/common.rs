@@ -1,4 +1,6 @@
+use actix_web::web;
 use async_openai::{config::OpenAIConfig, error::OpenAIError, types::ChatCompletionRequestMessage};
+use chrono::NaiveDate;
 use serde::{Deserialize, Serialize};
 use std::{
     cmp::PartialEq,
@@ -17,7 +19,7 @@ use teloxide::{
 #[derive(thiserror::Error, Debug)]
 pub enum AppError {
     #[error("OpenAI api error")]
-    OpenAI(#[from] OpenAIError),
+    OpenAI(Box<OpenAIError>),
     #[error("Teloxide error")]
     Teloxide(#[from] RequestError),
     #[error("Access denied")]
@@ -32,6 +34,12 @@ pub enum AppError {
     Database(#[from] sqlx::Error),
 }
 
+impl From<OpenAIError> for AppError {
+    fn from(err: OpenAIError) -> Self {
+        AppError::OpenAI(Box::new(err))
+    }
+}
+
 impl From<serde_json::Error> for AppError {
     fn from(err: serde_json::Error) -> Self {
         AppError::Json(err)
@@ -56,18 +64,58 @@ pub enum Commands {
     Help,
     #[command(description = "Roll the dice.")]
     Roll,
-    #[command(description = "Set prompt text.")]
+    #[command(description = "Set the system prompt.")]
     Prompt(String),
     #[command(description = "Chat with gpt.")]
     Chat(String),
+    #[command(description = "Send a prompt to several models side by side.")]
+    Compare(String),
     #[command(description = "Enter chat mode with chat gpt.")]
     Enter,
     #[command(description = "Exit chat mode with chat gpt.")]
     Exit,
-    #[command(description = "View chat histories.")]
+    #[command(description = "View active conversation history.")]
     View,
-    #[command(description = "Clear history chats.")]
+    #[command(description = "Clear active conversation history.")]
     Clear,
+    #[command(description = "Start a new named conversation.")]
+    New(String),
+    #[command(description = "List conversations.")]
+    Conversations,
+    #[command(description = "Switch conversation by name or id.")]
+    Switch(String),
+    #[command(description = "Rename active conversation.")]
+    Rename(String),
+    #[command(description = "Delete conversation by name or id.")]
+    Drop(String),
+    #[command(description = "Export active conversation: /export md or /export json.")]
+    Export(String),
+    #[command(description = "Import a conversation from an exported JSON file.")]
+    Import,
+    #[command(description = "List indexed documents.")]
+    Documents,
+    #[command(description = "Remove an indexed document by id or name.")]
+    Unindex(String),
+    #[command(description = "Remember a fact about you for all conversations.")]
+    Remember(String),
+    #[command(description = "Forget a remembered fact by id or text.")]
+    Forget(String),
+    #[command(description = "List remembered facts.")]
+    Memories,
+    #[command(description = "Schedule a prompt: <cron> | <prompt>.")]
+    Schedule(String),
+    #[command(description = "List scheduled prompts.")]
+    Jobs,
+    #[command(description = "Delete a scheduled prompt by id.")]
+    Unschedule(String),
+    #[command(description = "Show or set the timezone of schedules.")]
+    Timezone(String),
+    #[command(description = "Show AI usage and estimated cost.")]
+    Usage,
+    #[command(
+        description = "Add a transaction from text, e.g. spent 23.40 on groceries yesterday."
+    )]
+    Spent(String),
     #[command(description = "Remove last transaction")]
     Delete,
     #[command(description = "Reset bot")]
@@ -120,11 +168,18 @@ impl TransactionKind {
 
 pub type OpenAIClient = async_openai::Client<OpenAIConfig>;
 pub type ChatMessages = Vec<ChatCompletionRequestMessage>;
-pub type ChatHistories = HashMap<ChatId, ChatMessages>;
-pub type ChatHistoryState = Arc<Mutex<ChatHistories>>;
 
 pub type HandleResult = Result<(), AppError>;
 
+/// Latest insights report by user, kept with the figures it was written for.
+pub type InsightsCache = web::Data<Arc<Mutex<HashMap<i64, CachedInsights>>>>;
+
+pub struct CachedInsights {
+    /// Serialized figures without the report, a new transaction changes them.
+    pub figures: String,
+    pub report: String,
+}
+
 pub type BotDialogue = Dialogue<DialogueState, InMemStorage<DialogueState>>;
 
 #[derive(Clone, Default, Debug, PartialEq)]
@@ -134,6 +189,14 @@ pub enum DialogueState {
     InChatMode,
     WaitingForChatRequest,
     WaitingForNewPrompt,
+    WaitingForConversationName,
+    WaitingForConversationRename,
+    WaitingForConversationImport,
+    WaitingForPersonaName,
+    WaitingForPersonaPrompt {
+        id: Option<i64>,
+        name: String,
+    },
     InBudgetingMenu,
     InCategoriesMode,
     WaitingForNewCategoryName {
@@ -145,6 +208,26 @@ pub enum DialogueState {
         category_id: String,
         description: Option<String>,
     },
+    /// A receipt being corrected keeps its photo, `receipt_file_id` goes into the new proposal.
+    WaitingForTransactionText {
+        receipt_file_id: Option<String>,
+    },
+    WaitingForTransactionConfirmation {
+        proposal: TransactionProposal,
+    },
+}
+
+/// Transaction parsed from free text, only added to the database once confirmed.
+#[derive(Clone, Debug, PartialEq)]
+pub struct TransactionProposal {
+    pub amount: i64,
+    pub kind: TransactionKind,
+    pub category_id: i64,
+    pub category_name: String,
+    pub date: NaiveDate,
+    pub description: String,
+    /// Telegram file id of the receipt photo the proposal was read from.
+    pub receipt_file_id: Option<String>,
 }
 
 #[derive(Debug, Clone, Copy, EnumString, EnumIter)]
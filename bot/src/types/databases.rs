@@ -17,6 +17,38 @@ pub struct TransactionsDb {
     pub pool: SqlitePool,
 }
 
+pub struct ChatMessagesDb {
+    pub pool: SqlitePool,
+}
+
+pub struct ConversationsDb {
+    pub pool: SqlitePool,
+}
+
+pub struct UsageDb {
+    pub pool: SqlitePool,
+}
+
+pub struct PersonasDb {
+    pub pool: SqlitePool,
+}
+
+pub struct DocumentsDb {
+    pub pool: SqlitePool,
+}
+
+pub struct MemoriesDb {
+    pub pool: SqlitePool,
+}
+
+pub struct JobsDb {
+    pub pool: SqlitePool,
+}
+
+pub struct ComparisonsDb {
+    pub pool: SqlitePool,
+}
+
 impl UsersDb {
     pub fn new(pool: &SqlitePool) -> Self {
         Self { pool: pool.clone() }
@@ -34,3 +66,51 @@ impl TransactionsDb {
         Self { pool: pool.clone() }
     }
 }
+
+impl ChatMessagesDb {
+    pub fn new(pool: &SqlitePool) -> Self {
+        Self { pool: pool.clone() }
+    }
+}
+
+impl ConversationsDb {
+    pub fn new(pool: &SqlitePool) -> Self {
+        Self { pool: pool.clone() }
+    }
+}
+
+impl UsageDb {
+    pub fn new(pool: &SqlitePool) -> Self {
+        Self { pool: pool.clone() }
+    }
+}
+
+impl PersonasDb {
+    pub fn new(pool: &SqlitePool) -> Self {
+        Self { pool: pool.clone() }
+    }
+}
+
+impl DocumentsDb {
+    pub fn new(pool: &SqlitePool) -> Self {
+        Self { pool: pool.clone() }
+    }
+}
+
+impl MemoriesDb {
+    pub fn new(pool: &SqlitePool) -> Self {
+        Self { pool: pool.clone() }
+    }
+}
+
+impl JobsDb {
+    pub fn new(pool: &SqlitePool) -> Self {
+        Self { pool: pool.clone() }
+    }
+}
+
+impl ComparisonsDb {
+    pub fn new(pool: &SqlitePool) -> Self {
+        Self { pool: pool.clone() }
+    }
+}
@@ -26,6 +26,22 @@ pub enum OpenAIMenuItems {
     EnterChatMode,
     #[strum(serialize = "🟢 Chat Mode", props(Label = "🟢 Chat Mode"))]
     ExitChatMode,
+    #[strum(
+        serialize = "🆕 New Conversation",
+        props(Label = "🆕 New Conversation")
+    )]
+    NewConversation,
+    #[strum(serialize = "🗂 Conversations", props(Label = "🗂 Conversations"))]
+    Conversations,
+    #[strum(
+        serialize = "✏️ Rename Conversation",
+        props(Label = "✏️ Rename Conversation")
+    )]
+    RenameConversation,
+    #[strum(serialize = "🧠 Select Model", props(Label = "🧠 Select Model"))]
+    SelectModel,
+    #[strum(serialize = "🎭 Personas", props(Label = "🎭 Personas"))]
+    Personas,
     #[strum(serialize = "⚙️ Set AI Prompt", props(Label = "⚙️ Set AI Prompt"))]
     SetPrompt,
     #[strum(serialize = "📜 View History", props(Label = "📜 View History"))]
@@ -42,10 +58,14 @@ pub enum BudgetingMenuItems {
     Statistics,
     #[strum(serialize = "🧾 Transactions", props(Label = "🧾 Transactions"))]
     Transactions,
+    #[strum(serialize = "🧠 Insights", props(Label = "🧠 Insights"))]
+    Insights,
     #[strum(serialize = "➕ Add Income", props(Label = "➕ Add Income"))]
     AddIncome,
     #[strum(serialize = "➖ Add Spending", props(Label = "➖ Add Spending"))]
     AddSpending,
+    #[strum(serialize = "🪄 Quick Add", props(Label = "🪄 Quick Add"))]
+    QuickAdd,
     #[strum(serialize = "⚙️ Settings", props(Label = "⚙️ Settings"))]
     Settings,
     #[strum(serialize = "📋 Categories", props(Label = "📋 Categories"))]
@@ -1,4 +1,5 @@
 pub mod auth;
+pub mod chat;
 pub mod common;
 pub mod databases;
 pub mod keyboard;
@@ -24,3 +24,101 @@ pub struct TransactionRow {
     pub category_name: String,
     pub description: String,
 }
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct ChatMessageRow {
+    pub id: i64,
+    pub chat_id: i64,
+    pub conversation_id: i64,
+    pub role: String,
+    pub content: String,
+    pub tokens: i64,
+    pub kind: String,
+    pub tool_calls: Option<String>,
+    pub tool_call_id: Option<String>,
+    pub image: Option<String>,
+    pub created_at: NaiveDateTime,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ConversationRow {
+    pub id: i64,
+    pub chat_id: i64,
+    pub name: String,
+    pub model: Option<String>,
+    pub parent_id: Option<i64>,
+    pub created_at: NaiveDateTime,
+    pub messages_count: i64,
+}
+
+/// Chat message a Telegram message of the bot was rendered from.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct MessageLinkRow {
+    pub chat_message_id: i64,
+    pub conversation_id: i64,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct PersonaRow {
+    pub id: i64,
+    pub chat_id: i64,
+    pub name: String,
+    pub prompt: String,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct DocumentRow {
+    pub id: i64,
+    pub name: String,
+    pub chunks: i64,
+    pub created_at: NaiveDateTime,
+}
+
+/// Chunk of an indexed document, `embedding` holds little-endian `f32` values.
+#[derive(Debug, Clone)]
+pub struct DocumentChunkRow {
+    pub document_name: String,
+    pub position: i64,
+    pub content: String,
+    pub embedding: Vec<u8>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct MemoryRow {
+    pub id: i64,
+    pub content: String,
+}
+
+/// Scheduled prompt, `next_run` is a unix timestamp.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct JobRow {
+    pub id: i64,
+    pub chat_id: i64,
+    pub schedule: String,
+    pub prompt: String,
+    pub next_run: i64,
+}
+
+/// An answer of a side by side comparison together with the prompt it answers.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ComparisonAnswerRow {
+    pub id: i64,
+    pub comparison_id: i64,
+    pub conversation_id: i64,
+    pub prompt: String,
+    /// Latest message of the conversation the answers were given after.
+    pub last_message_id: i64,
+    pub picked_answer_id: Option<i64>,
+    pub model: String,
+    pub content: String,
+    /// JSON array of the Telegram messages the answer was rendered into.
+    pub message_ids: String,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct UsageTotalRow {
+    pub model: String,
+    pub requests: i64,
+    pub prompt_tokens: i64,
+    pub completion_tokens: i64,
+}
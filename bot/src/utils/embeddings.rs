@@ -0,0 +1,106 @@
+/// Splits text into chunks of at most `size` characters, consecutive chunks share
+/// about `overlap` characters. Cuts are moved back to whitespace when possible.
+pub fn chunk_text(text: &str, size: usize, overlap: usize) -> Vec<String> {
+    let chars: Vec<char> = text.chars().collect();
+    let size = size.max(1);
+    let overlap = overlap.min(size / 2);
+    let mut chunks = Vec::new();
+    let mut start = 0;
+
+    while start < chars.len() {
+        let mut end = (start + size).min(chars.len());
+
+        if end < chars.len() {
+            if let Some(cut) = (start + size / 2..end)
+                .rev()
+                .find(|&index| chars[index].is_whitespace())
+            {
+                end = cut;
+            }
+        }
+
+        let chunk: String = chars[start..end].iter().collect();
+
+        if !chunk.trim().is_empty() {
+            chunks.push(chunk.trim().to_string());
+        }
+
+        if end == chars.len() {
+            break;
+        }
+
+        start = (end - overlap).max(start + 1);
+
+        while start < end && !chars[start - 1].is_whitespace() {
+            start += 1;
+        }
+
+        while start < chars.len() && chars[start].is_whitespace() {
+            start += 1;
+        }
+    }
+
+    chunks
+}
+
+pub fn cosine_similarity(a: &[f32], b: &[f32]) -> f32 {
+    let dot: f32 = a.iter().zip(b).map(|(x, y)| x * y).sum();
+    let norm_a = a.iter().map(|x| x * x).sum::<f32>().sqrt();
+    let norm_b = b.iter().map(|x| x * x).sum::<f32>().sqrt();
+
+    if norm_a == 0.0 || norm_b == 0.0 {
+        return 0.0;
+    }
+
+    dot / (norm_a * norm_b)
+}
+
+/// Stores a vector as little-endian `f32` bytes for a SQLite BLOB.
+pub fn encode_vector(vector: &[f32]) -> Vec<u8> {
+    vector
+        .iter()
+        .flat_map(|value| value.to_le_bytes())
+        .collect()
+}
+
+pub fn decode_vector(bytes: &[u8]) -> Vec<f32> {
+    bytes
+        .chunks_exact(4)
+        .map(|chunk| f32::from_le_bytes([chunk[0], chunk[1], chunk[2], chunk[3]]))
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn chunks_with_overlap_on_whitespace() {
+        let chunks = chunk_text("one two three four five six", 10, 4);
+
+        assert_eq!(
+            chunks,
+            vec!["one two", "two three", "four five", "five six"]
+        );
+    }
+
+    #[test]
+    fn keeps_short_text_in_one_chunk() {
+        assert_eq!(chunk_text("  short  ", 100, 10), vec!["short"]);
+        assert!(chunk_text("   ", 100, 10).is_empty());
+    }
+
+    #[test]
+    fn scores_vectors_by_direction() {
+        assert!((cosine_similarity(&[1.0, 0.0], &[2.0, 0.0]) - 1.0).abs() < 1e-6);
+        assert!(cosine_similarity(&[1.0, 0.0], &[0.0, 1.0]).abs() < 1e-6);
+        assert_eq!(cosine_similarity(&[0.0, 0.0], &[1.0, 0.0]), 0.0);
+    }
+
+    #[test]
+    fn round_trips_vectors_through_bytes() {
+        let vector = vec![0.5, -1.25, 3.0];
+
+        assert_eq!(decode_vector(&encode_vector(&vector)), vector);
+    }
+}
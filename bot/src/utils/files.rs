@@ -0,0 +1,23 @@
+use base64::{engine::general_purpose::STANDARD, Engine};
+use teloxide::{net::Download, prelude::*, types::FileId, types::PhotoSize};
+
+use crate::types::common::AppError;
+
+/// Downloads a file through the Bot API.
+pub async fn download(bot: &Bot, file_id: &FileId) -> Result<Vec<u8>, AppError> {
+    let file = bot.get_file(file_id.clone()).await?;
+    let mut data = Vec::new();
+
+    bot.download_file(&file.path, &mut data)
+        .await
+        .map_err(|e| AppError::InternalError(format!("File download failed: {}", e)))?;
+
+    Ok(data)
+}
+
+/// Downloads the photo as a data URL, Telegram stores photos as JPEG.
+pub async fn download_photo(bot: &Bot, photo: &PhotoSize) -> Result<String, AppError> {
+    let data = download(bot, &photo.file.id).await?;
+
+    Ok(format!("data:image/jpeg;base64,{}", STANDARD.encode(data)))
+}
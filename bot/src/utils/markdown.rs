@@ -8,3 +8,327 @@ pub fn escape_markdown_v2(text: &str) -> String {
         })
         .collect()
 }
+
+/// Telegram rejects messages longer than 4096 characters, some room is left for the
+/// markup a plain text fallback loses.
+pub const MESSAGE_LIMIT: usize = 4000;
+
+const FENCE: &str = "```";
+
+fn escape_code(text: &str) -> String {
+    text.replace('\\', "\\\\").replace('`', "\\`")
+}
+
+fn escape_url(text: &str) -> String {
+    text.replace('\\', "\\\\").replace(')', "\\)")
+}
+
+fn find_seq(chars: &[char], from: usize, pattern: &[char]) -> Option<usize> {
+    (from..chars.len().saturating_sub(pattern.len() - 1)).find(|&i| chars[i..].starts_with(pattern))
+}
+
+fn collect(chars: &[char]) -> String {
+    chars.iter().collect()
+}
+
+/// Wraps `chars[from..]` up to the closing `delimiter` into Telegram `marker`s,
+/// returns the converted text and the index after the closing delimiter.
+fn wrap(chars: &[char], from: usize, delimiter: &[char], marker: char) -> Option<(String, usize)> {
+    let start = from + delimiter.len();
+    let end = find_seq(chars, start, delimiter)?;
+
+    if end == start || chars[start].is_whitespace() || chars[end - 1].is_whitespace() {
+        return None;
+    }
+
+    let inner = inline_to_markdown_v2(&collect(&chars[start..end]));
+
+    Some((format!("{marker}{inner}{marker}"), end + delimiter.len()))
+}
+
+fn link(chars: &[char], from: usize) -> Option<(String, usize)> {
+    let close = find_seq(chars, from + 1, &[']', '('])?;
+    let end = find_seq(chars, close + 2, &[')'])?;
+
+    let text = inline_to_markdown_v2(&collect(&chars[from + 1..close]));
+    let url = escape_url(&collect(&chars[close + 2..end]));
+
+    Some((format!("[{text}]({url})"), end + 1))
+}
+
+fn inline_to_markdown_v2(text: &str) -> String {
+    let chars: Vec<char> = text.chars().collect();
+    let mut output = String::new();
+    let mut i = 0;
+
+    while i < chars.len() {
+        let rest = &chars[i..];
+
+        let converted = if rest[0] == '`' {
+            find_seq(&chars, i + 1, &['`']).map(|end| {
+                (
+                    format!("`{}`", escape_code(&collect(&chars[i + 1..end]))),
+                    end + 1,
+                )
+            })
+        } else if rest.starts_with(&['*', '*']) {
+            wrap(&chars, i, &['*', '*'], '*')
+        } else if rest.starts_with(&['_', '_']) {
+            wrap(&chars, i, &['_', '_'], '*')
+        } else if rest.starts_with(&['~', '~']) {
+            wrap(&chars, i, &['~', '~'], '~')
+        } else if rest[0] == '*' {
+            wrap(&chars, i, &['*'], '_')
+        } else if rest[0] == '[' {
+            link(&chars, i)
+        } else {
+            None
+        };
+
+        match converted {
+            Some((converted, next)) => {
+                output.push_str(&converted);
+                i = next;
+            }
+            None => {
+                output.push_str(&escape_markdown_v2(&rest[0].to_string()));
+                i += 1;
+            }
+        }
+    }
+
+    output
+}
+
+fn list_item(line: &str) -> Option<(String, &str)> {
+    let trimmed = line.trim_start();
+    let indent = " ".repeat(line.len() - trimmed.len());
+
+    for bullet in ["- ", "* ", "+ "] {
+        if let Some(item) = trimmed.strip_prefix(bullet) {
+            return Some((format!("{indent}• "), item));
+        }
+    }
+
+    let digits = trimmed.chars().take_while(|c| c.is_ascii_digit()).count();
+
+    if digits > 0 {
+        if let Some(item) = trimmed[digits..].strip_prefix(". ") {
+            return Some((format!("{indent}{}\\. ", &trimmed[..digits]), item));
+        }
+    }
+
+    None
+}
+
+/// Converts model Markdown into Telegram MarkdownV2. Unclosed inline markup is
+/// escaped and an unclosed code block is closed, so partial streamed text renders too.
+pub fn markdown_to_telegram_v2(text: &str) -> String {
+    let mut lines: Vec<String> = Vec::new();
+    let mut in_code = false;
+
+    for line in text.lines() {
+        if line.trim_start().starts_with(FENCE) {
+            let language = line.trim_start().trim_start_matches('`').trim();
+
+            lines.push(if in_code {
+                FENCE.to_string()
+            } else {
+                format!("{FENCE}{}", escape_code(language))
+            });
+
+            in_code = !in_code;
+        } else if in_code {
+            lines.push(escape_code(line));
+        } else if let Some(heading) = line
+            .strip_prefix('#')
+            .map(|heading| heading.trim_start_matches('#'))
+            .filter(|heading| heading.starts_with(' '))
+        {
+            lines.push(format!("*{}*", inline_to_markdown_v2(heading.trim())));
+        } else if let Some((marker, item)) = list_item(line) {
+            lines.push(format!("{marker}{}", inline_to_markdown_v2(item)));
+        } else if let Some(quote) = line.strip_prefix('>') {
+            lines.push(format!(">{}", inline_to_markdown_v2(quote.trim_start())));
+        } else {
+            lines.push(inline_to_markdown_v2(line));
+        }
+    }
+
+    if in_code {
+        lines.push(FENCE.to_string());
+    }
+
+    lines.join("\n")
+}
+
+/// Splits model Markdown into parts of at most `limit` characters on line
+/// boundaries, code blocks cut by a split are closed and reopened.
+pub fn split_message(text: &str, limit: usize) -> Vec<String> {
+    split_measured(text, limit, 1, |part| part.chars().count())
+}
+
+/// Like `split_message`, but every part fits `limit` once converted with
+/// `markdown_to_telegram_v2`, whose escaping can double the length of dense text.
+pub fn split_markdown(text: &str, limit: usize) -> Vec<String> {
+    split_measured(text, limit, 2, |part| {
+        markdown_to_telegram_v2(part).chars().count()
+    })
+}
+
+/// `growth` is how many times `measure` may exceed the raw length, lines longer than
+/// a part are cut so that a single piece always fits.
+fn split_measured(
+    text: &str,
+    limit: usize,
+    growth: usize,
+    measure: impl Fn(&str) -> usize,
+) -> Vec<String> {
+    let mut parts: Vec<String> = Vec::new();
+    let mut current = String::new();
+    let mut fence: Option<String> = None;
+
+    for line in text.split('\n') {
+        let mut pieces: Vec<String> = line
+            .chars()
+            .collect::<Vec<char>>()
+            .chunks((limit.saturating_sub(2 * FENCE.len() + 2) / growth).max(1))
+            .map(|chunk| chunk.iter().collect())
+            .collect();
+
+        if pieces.is_empty() {
+            pieces.push(String::new());
+        }
+
+        for piece in pieces {
+            let mut candidate = format!("{}\n{}", current, piece);
+
+            if fence.is_some() {
+                candidate.push('\n');
+                candidate.push_str(FENCE);
+            }
+
+            if !current.is_empty() && measure(&candidate) > limit {
+                if fence.is_some() {
+                    current.push('\n');
+                    current.push_str(FENCE);
+                }
+
+                parts.push(std::mem::take(&mut current));
+
+                if let Some(opening) = &fence {
+                    current.push_str(opening);
+                }
+            }
+
+            if !current.is_empty() {
+                current.push('\n');
+            }
+
+            current.push_str(&piece);
+        }
+
+        if line.trim_start().starts_with(FENCE) {
+            fence = match fence {
+                Some(_) => None,
+                None => Some(line.trim_start().to_string()),
+            };
+        }
+    }
+
+    if !current.is_empty() {
+        parts.push(current);
+    }
+
+    parts
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn escapes_plain_text() {
+        assert_eq!(
+            markdown_to_telegram_v2("Total: 1.5 (approx)!"),
+            "Total: 1\\.5 \\(approx\\)\\!"
+        );
+    }
+
+    #[test]
+    fn converts_inline_markup() {
+        assert_eq!(
+            markdown_to_telegram_v2("**bold**, *italic*, ~~gone~~ and `a_b`"),
+            "*bold*, _italic_, ~gone~ and `a_b`"
+        );
+    }
+
+    #[test]
+    fn converts_links() {
+        assert_eq!(
+            markdown_to_telegram_v2("see [the docs](https://example.com/a_b)."),
+            "see [the docs](https://example.com/a_b)\\."
+        );
+    }
+
+    #[test]
+    fn converts_headings_and_lists() {
+        assert_eq!(
+            markdown_to_telegram_v2("## Plan\n- one\n2. two\n> note"),
+            "*Plan*\n• one\n2\\. two\n>note"
+        );
+    }
+
+    #[test]
+    fn keeps_code_blocks_and_closes_unfinished_ones() {
+        assert_eq!(
+            markdown_to_telegram_v2("```rust\nlet a = `x`;\n```"),
+            "```rust\nlet a = \\`x\\`;\n```"
+        );
+        assert_eq!(
+            markdown_to_telegram_v2("```\nlet a = 1;"),
+            "```\nlet a = 1;\n```"
+        );
+    }
+
+    #[test]
+    fn escapes_unclosed_markup() {
+        assert_eq!(markdown_to_telegram_v2("2 * 3 **bo"), "2 \\* 3 \\*\\*bo");
+    }
+
+    #[test]
+    fn splits_long_text_on_lines() {
+        let text = "aaaaaaaaaa\nbbbbbbbbbb\ncccccccccc";
+
+        assert_eq!(
+            split_message(text, 25),
+            vec!["aaaaaaaaaa\nbbbbbbbbbb", "cccccccccc"]
+        );
+        assert_eq!(split_message(text, 100), vec![text]);
+    }
+
+    #[test]
+    fn splits_on_converted_length() {
+        let dense = "Total: 1.5 (approx)!".repeat(40);
+        let text = format!("{}\n{}", vec![dense; 12].join("\n"), ".".repeat(5000));
+
+        let parts = split_markdown(&text, MESSAGE_LIMIT);
+
+        assert!(parts.len() > 1);
+        assert!(parts
+            .iter()
+            .all(|part| markdown_to_telegram_v2(part).chars().count() <= MESSAGE_LIMIT));
+        assert_eq!(parts.concat().replace('\n', ""), text.replace('\n', ""));
+    }
+
+    #[test]
+    fn reopens_code_blocks_across_parts() {
+        let text = "```rust\nline one\nline two\n```";
+        let parts = split_message(text, 24);
+
+        assert_eq!(
+            parts,
+            vec!["```rust\nline one\n```", "```rust\nline two\n```"]
+        );
+    }
+}
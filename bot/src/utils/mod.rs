@@ -1,4 +1,8 @@
+pub mod embeddings;
+pub mod files;
 pub mod markdown;
+pub mod schedule;
 pub mod statistics;
 pub mod strings;
+pub mod tokens;
 pub mod transactions;
@@ -0,0 +1,150 @@
+use chrono::DateTime;
+use chrono_tz::Tz;
+use cron::{
+    error::{Error, ErrorKind},
+    Schedule,
+};
+use std::str::FromStr;
+
+const WEEKDAYS: [&str; 8] = ["Sun", "Mon", "Tue", "Wed", "Thu", "Fri", "Sat", "Sun"];
+
+/// Parses a cron expression. Five field expressions (minute, hour, day of month, month,
+/// day of week) are crontab ones: they run at second zero and number the days of the
+/// week from 0 (or 7) for Sunday. Six and seven field ones are taken as they are.
+pub fn parse(expression: &str) -> Result<Schedule, Error> {
+    let fields: Vec<&str> = expression.split_whitespace().collect();
+
+    match fields.as_slice() {
+        [minute, hour, day, month, weekday] => Schedule::from_str(&format!(
+            "0 {} {} {} {} {}",
+            minute,
+            hour,
+            day,
+            month,
+            weekday_names(weekday)?
+        )),
+        _ => Schedule::from_str(&fields.join(" ")),
+    }
+}
+
+/// The cron crate numbers the days of the week from 1 for Sunday, numeric crontab days
+/// are rewritten as names, which both read the same.
+fn weekday_names(field: &str) -> Result<String, Error> {
+    let items = field
+        .split(',')
+        .map(|item| {
+            let (range, step) = match item.split_once('/') {
+                Some((range, step)) => (range, Some(step)),
+                None => (item, None),
+            };
+
+            let names = match range.split_once('-') {
+                // A range up to Sunday wraps around the week of the cron crate.
+                Some((start, "7")) if start != "7" && step.is_none() => {
+                    format!("{}-Sat,Sun", weekday(start)?)
+                }
+                Some((start, end)) => format!("{}-{}", weekday(start)?, weekday(end)?),
+                None => weekday(range)?,
+            };
+
+            Ok(match step {
+                Some(step) => format!("{}/{}", names, step),
+                None => names,
+            })
+        })
+        .collect::<Result<Vec<String>, Error>>()?;
+
+    Ok(items.join(","))
+}
+
+/// Day name of a crontab day number, names and wildcards are kept.
+fn weekday(value: &str) -> Result<String, Error> {
+    match value.parse::<usize>() {
+        Ok(number) => WEEKDAYS
+            .get(number)
+            .map(|name| name.to_string())
+            .ok_or_else(|| ErrorKind::Expression(format!("Invalid day of week: {}", value)).into()),
+        Err(_) => Ok(value.to_string()),
+    }
+}
+
+/// Unix timestamp of the first run strictly after `after`, in the given timezone.
+pub fn next_run(schedule: &Schedule, timezone: Tz, after: i64) -> Option<i64> {
+    let after = DateTime::from_timestamp(after, 0)?.with_timezone(&timezone);
+
+    schedule.after(&after).next().map(|next| next.timestamp())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::{NaiveDate, TimeZone};
+
+    fn timestamp(timezone: Tz, day: u32, hour: u32, minute: u32) -> i64 {
+        let local = NaiveDate::from_ymd_opt(2024, 3, day)
+            .and_then(|date| date.and_hms_opt(hour, minute, 0))
+            .unwrap();
+
+        timezone.from_local_datetime(&local).unwrap().timestamp()
+    }
+
+    #[test]
+    fn five_fields_run_at_second_zero() {
+        assert!(parse("0 8 * * Mon-Fri").is_ok());
+        assert!(parse("0 0 8 * * Mon-Fri").is_ok());
+        assert!(parse("every morning").is_err());
+    }
+
+    #[test]
+    fn next_run_respects_timezone_and_weekdays() {
+        let schedule = parse("0 8 * * Mon-Fri").unwrap();
+        let berlin: Tz = "Europe/Berlin".parse().unwrap();
+
+        // Friday 2024-03-01 09:00 in Berlin, the next weekday is Monday the 4th.
+        let after = timestamp(berlin, 1, 9, 0);
+
+        assert_eq!(
+            next_run(&schedule, berlin, after),
+            Some(timestamp(berlin, 4, 8, 0))
+        );
+    }
+
+    #[test]
+    fn numeric_weekdays_follow_crontab() {
+        let utc = Tz::UTC;
+        // Friday 2024-03-01 09:00.
+        let after = timestamp(utc, 1, 9, 0);
+        let next = |expression: &str| next_run(&parse(expression).unwrap(), utc, after);
+
+        assert_eq!(next("0 8 * * 1-5"), Some(timestamp(utc, 4, 8, 0)));
+        assert_eq!(next("0 8 * * 0"), Some(timestamp(utc, 3, 8, 0)));
+        assert_eq!(next("0 8 * * 7"), Some(timestamp(utc, 3, 8, 0)));
+        assert_eq!(next("0 8 * * 6,1"), Some(timestamp(utc, 2, 8, 0)));
+        assert_eq!(next("0 8 * * 0-0"), Some(timestamp(utc, 3, 8, 0)));
+        assert!(parse("0 8 * * 8").is_err());
+    }
+
+    #[test]
+    fn ranges_up_to_sunday_wrap_around() {
+        let utc = Tz::UTC;
+        let schedule = parse("0 8 * * 6-7").unwrap();
+
+        // Thursday 2024-02-29 09:00, the range covers Saturday and Sunday only.
+        let after = NaiveDate::from_ymd_opt(2024, 2, 29)
+            .and_then(|date| date.and_hms_opt(9, 0, 0))
+            .unwrap()
+            .and_utc()
+            .timestamp();
+        let saturday = next_run(&schedule, utc, after).unwrap();
+
+        assert_eq!(saturday, timestamp(utc, 2, 8, 0));
+        assert_eq!(
+            next_run(&schedule, utc, saturday),
+            Some(timestamp(utc, 3, 8, 0))
+        );
+        assert_eq!(
+            next_run(&schedule, utc, timestamp(utc, 3, 8, 0)),
+            Some(timestamp(utc, 9, 8, 0))
+        );
+    }
+}
@@ -0,0 +1,16 @@
+use tiktoken_rs::{
+    cl100k_base_singleton, o200k_base_singleton,
+    tokenizer::{get_tokenizer, Tokenizer},
+};
+
+/// Tokens added by the chat format around every message (role, separators).
+const MESSAGE_OVERHEAD: usize = 4;
+
+pub fn count_tokens(model: &str, text: &str) -> usize {
+    let bpe = match get_tokenizer(model) {
+        Some(Tokenizer::O200kBase) => o200k_base_singleton(),
+        _ => cl100k_base_singleton(),
+    };
+
+    bpe.encode_with_special_tokens(text).len() + MESSAGE_OVERHEAD
+}
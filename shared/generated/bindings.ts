@@ -10,6 +10,26 @@ export type BudgetingTransaction = {
   accumulatdedAmount: number;
 };
 
+export type InsightsCategory = {
+  name: string;
+  amount: number;
+  previousAmount: number;
+  delta: number;
+};
+
+export type InsightsResponse = {
+  currency: string;
+  year: number;
+  month: number;
+  income: number;
+  spending: number;
+  previousIncome: number;
+  previousSpending: number;
+  categories: Array<InsightsCategory>;
+  largestTransactions: Array<OverviewTransaction>;
+  report: string;
+};
+
 export type LoginPayload = { initData: string };
 
 export type LoginResponse = { accessToken: string; userId: string };
@@ -103,6 +103,32 @@ pub struct YearlySummary {
     pub monthly_spending_summaries: Vec<MonthlySpendingSummary>,
 }
 
+#[derive(Deserialize, Serialize, TS)]
+#[ts(export, export_to = "../generated/bindings.ts")]
+#[serde(rename_all = "camelCase")]
+pub struct InsightsCategory {
+    pub name: String,
+    pub amount: f64,
+    pub previous_amount: f64,
+    pub delta: f64,
+}
+
+#[derive(Deserialize, Serialize, TS)]
+#[ts(export, export_to = "../generated/bindings.ts")]
+#[serde(rename_all = "camelCase")]
+pub struct InsightsResponse {
+    pub currency: String,
+    pub year: u32,
+    pub month: u32,
+    pub income: f64,
+    pub spending: f64,
+    pub previous_income: f64,
+    pub previous_spending: f64,
+    pub categories: Vec<InsightsCategory>,
+    pub largest_transactions: Vec<OverviewTransaction>,
+    pub report: String,
+}
+
 #[derive(Deserialize, Serialize, TS)]
 #[ts(export, export_to = "../generated/bindings.ts")]
 #[serde(rename_all = "camelCase")]